@@ -12,10 +12,6 @@ unit DuallUtils;
 interface
 
 uses
-{$IFDEF MSWINDOWS}
-  Windows,
-  Classes,
-{$ENDIF}
   SysUtils,
 {$IFNDEF FPC}
   IOUtils,
@@ -104,6 +100,14 @@ begin
   raise EdUtils.CreateFmt(SUnknownErrorInFunction, [AFuncName]);
 end;
 
+function ExitStatusToCode(const AStatus: DU_EXIT_STATUS): Integer; inline;
+begin
+  if AStatus.kind = DU_EXIT_CODE then
+    Result := AStatus.code
+  else
+    Result := 128 + AStatus.code;
+end;
+
 function ArrayToCArray(const AArray: array of string;
   out AOutput: TArray<Pcchar>): PPcchar;
 var
@@ -120,49 +124,6 @@ begin
   Result := @AOutput[0];
 end;
 
-{$IFDEF MSWINDOWS}
-
-function ArrayToString(const ASeparator: string;
-  const AArray: array of string): string;
-var
-  I, L: Integer;
-begin
-  L := Length(AArray);
-  if L = 0 then
-    Exit(EmptyStr);
-  Result := AArray[0];
-  for I := 1 to Pred(L) do
-    Result := Concat(Result, ASeparator, AArray[I]);
-end;
-
-function BuildEnvBlock(const AArray: array of string): TBytes;
-var
-  M: TBytesStream;
-  S: string;
-  C: Char;
-begin
-  M := TBytesStream.Create;
-  try
-    C := #0;
-    for S in AArray do
-    begin
-{$IFDEF FPC}
-      M.Write(S[1], Length(S));
-{$ELSE}
-      M.Write(TEncoding.Unicode.GetBytes(S), TEncoding.Unicode.GetByteCount(S));
-{$ENDIF}
-      M.Write(C, SizeOf(Char));
-    end;
-    M.Write(C, SizeOf(Char));
-    Result := M.Bytes;
-    SetLength(Result, M.Size);
-  finally
-    M.Destroy;
-  end;
-end;
-
-{$ENDIF}
-
 { dUtils }
 
 class procedure dUtils.Load(const ALibraryName: TFileName);
@@ -260,65 +221,30 @@ class function dUtils.Spawn(const AProgram: TFileName; const AWorkDir: string;
   const AArgs, AEnvs: array of string; {$IFDEF MSWINDOWS}AHidden,{$ENDIF}
   AWaiting: Boolean; out AExitCode: Integer): Boolean;
 var
-{$IFDEF MSWINDOWS}
-  SI: TStartupInfo;
-  PI: TProcessInformation;
-  C: string;
-  W: PChar;
-  B: TBytes;
-  F: DWORD;
-{$ENDIF}
   M: TMarshaller;
   A, E: TArray<Pcchar>;
+  O: DU_SPAWN_OPTIONS;
+  S: DU_EXIT_STATUS;
   R: cint;
 begin
   libduallutils.Check;
+  O := Default(DU_SPAWN_OPTIONS);
+  O.workdir := M.ToCNullableString(AWorkDir);
 {$IFDEF MSWINDOWS}
-  if AHidden then
-  begin
-    SI := Default(TStartupInfo);
-    SI.cb := SizeOf(TStartupInfo);
-    SI.dwFlags := STARTF_USESHOWWINDOW;
-    SI.wShowWindow := SW_HIDE;
-    PI := Default(TProcessInformation);
-    C := AProgram;
-    if Length(AArgs) > 0 then
-      C := Concat(AProgram, ' ', ArrayToString(' ', AArgs));
-    if AWorkDir.IsEmpty then
-      W := nil
-    else
-      W := PChar(AWorkDir + #0);
-    if Length(AEnvs) > 0 then
-      B := BuildEnvBlock(AEnvs)
-    else
-      B := nil;
-    F := CREATE_NEW_CONSOLE or NORMAL_PRIORITY_CLASS
-{$IFNDEF FPC}or CREATE_UNICODE_ENVIRONMENT{$ENDIF};
-    if CreateProcess(nil, PChar(C + #0), nil, nil, False, F, B, W, SI, PI) then
-    try
-      if WaitForSingleObject(PI.hProcess, INFINITE) <> WAIT_FAILED then
-      begin
-        AExitCode := 0;
-        GetExitCodeProcess(PI.hProcess, DWORD(AExitCode));
-        Exit(True);
-      end;
-    finally
-      CloseHandle(PI.hProcess);
-      CloseHandle(PI.hThread);
-    end;
-    if GetLastError = ERROR_FILE_NOT_FOUND then
-      Exit(False);
-    RaiseUnknownErrorInFunction('dUtils.Spawn');
-  end;
+  O.hidden := AHidden;
 {$ENDIF}
-  R := libduallutils.du_spawn(M.ToCString(AProgram),
-    M.ToCNullableString(AWorkDir), ArrayToCArray(AArgs, A),
-    ArrayToCArray(AEnvs, E), AWaiting, @AExitCode);
+  O.inherit_handles := True;
+  S := Default(DU_EXIT_STATUS);
+  R := libduallutils.du_spawn_ex(M.ToCString(AProgram),
+    ArrayToCArray(AArgs, A), ArrayToCArray(AEnvs, E), nil, @O, AWaiting, 0,
+    nil, nil, @S);
   case R of
     -1: RaiseInvalidFunctionArgument;
     -2: Exit(False);
     -3: RaiseUnknownErrorInFunction('dUtils.Spawn');
   end;
+  if AWaiting then
+    AExitCode := ExitStatusToCode(S);
   Result := True;
 end;
 
@@ -338,22 +264,31 @@ class function dUtils.Execute(const AProgram: TFileName; const AWorkDir: string;
 var
   M: TMarshaller;
   A, E: TArray<Pcchar>;
-  SO, SE: Pcchar;
+  SO, SE: Pcvoid;
+  LO, LE: csize_t;
+  O: DU_SPAWN_OPTIONS;
+  S: DU_EXIT_STATUS;
   R: cint;
 begin
   libduallutils.Check;
-  R := libduallutils.du_execute(M.ToCString(AProgram),
-    M.ToCNullableString(AWorkDir), ArrayToCArray(AArgs, A),
-    ArrayToCArray(AEnvs, E), @SO, @SE, @AExitCode);
+  O := Default(DU_SPAWN_OPTIONS);
+  O.workdir := M.ToCNullableString(AWorkDir);
+  O.inherit_handles := True;
+  R := libduallutils.du_execute_ex(M.ToCString(AProgram),
+    ArrayToCArray(AArgs, A), ArrayToCArray(AEnvs, E), nil, @O, nil, 0, 0, 0,
+    DU_OVERFLOW_DISCARD, M.ToCString('UTF-8'), @SO, @LO, @SE, @LE, nil, @S);
   case R of
     -1: RaiseInvalidFunctionArgument;
     -2: Exit(False);
     -3: RaiseUnknownErrorInFunction('dUtils.Execute');
   end;
-  AOutput := TMarshal.ToString(SO);
-  du_dispose(SO);
-  AError := TMarshal.ToString(SE);
-  du_dispose(SE);
+  AOutput := TEncoding.UTF8.GetString(
+    TMarshal.ToBytes(MarshaledAString(SO), LO));
+  du_dispose_bytes(SO, LO);
+  AError := TEncoding.UTF8.GetString(
+    TMarshal.ToBytes(MarshaledAString(SE), LE));
+  du_dispose_bytes(SE, LE);
+  AExitCode := ExitStatusToCode(S);
   Result := True;
 end;
 
@@ -67,13 +67,23 @@ resourcestring
 
 type
   Pcvoid = Pointer;
+  PPcvoid = ^Pcvoid;
   Pcchar = MarshaledAString;
   PPcchar = PMarshaledAString;
   cchar = Byte;
   cbool = Boolean;
+  Pcbool = ^cbool;
   cint = Integer;
+  cushort = Word;
+  cuint = Cardinal;
+  Pcuint = ^cuint;
+  cuint64 = UInt64;
+  Pcuint64 = ^cuint64;
+  cint64 = Int64;
+  Pcint64 = ^cint64;
   Pcint= PInteger;
   csize_t = NativeUInt;
+  Pcsize_t = ^csize_t;
   cenum = cint;
 
   EduLibNotLoaded = class(EFileNotFoundException);
@@ -85,9 +95,127 @@ const
   DU_LK_NUMLOCK = 1;
   DU_LK_SCROLLLOCK = 2;
 
+type
+  DU_OTP_ALGORITHM = cenum;
+const
+  DU_OTP_SHA1 = 0;
+  DU_OTP_SHA256 = 1;
+
+type
+  DU_ENV_OPTIONS = record
+    clear: cbool;
+    inherit: PPcchar;
+    remove: PPcchar;
+  end;
+  PDU_ENV_OPTIONS = ^DU_ENV_OPTIONS;
+
+type
+  DU_EXIT_KIND = cenum;
+const
+  DU_EXIT_CODE = 0;
+  DU_EXIT_SIGNAL = 1;
+  DU_EXIT_CORE_DUMP = 2;
+
+type
+  DU_EXIT_STATUS = record
+    kind: DU_EXIT_KIND;
+    code: cint;
+  end;
+  PDU_EXIT_STATUS = ^DU_EXIT_STATUS;
+
+type
+  DU_OVERFLOW = cenum;
+const
+  DU_OVERFLOW_DISCARD = 0;
+  DU_OVERFLOW_KILL = 1;
+
+type
+  DU_REDIRECT_MODE = cenum;
+const
+  DU_REDIRECT_INHERIT = 0;
+  DU_REDIRECT_NULL = 1;
+  DU_REDIRECT_TRUNCATE = 2;
+  DU_REDIRECT_APPEND = 3;
+  DU_REDIRECT_STDOUT = 4;
+
+type
+  DU_REDIRECT = record
+    mode: DU_REDIRECT_MODE;
+    path: Pcchar;
+  end;
+  PDU_REDIRECT = ^DU_REDIRECT;
+
+type
+  DU_CREDENTIALS = record
+    user: Pcchar;
+    group: Pcchar;
+    groups: PPcchar;
+  end;
+  PDU_CREDENTIALS = ^DU_CREDENTIALS;
+
+type
+  DU_LIMITS = record
+    address_space: cuint64;
+    cpu_time: cuint64;
+    open_files: cuint64;
+    nice: cint;
+    umask: cint;
+  end;
+  PDU_LIMITS = ^DU_LIMITS;
+
+type
+  DU_SPAWN_OPTIONS = record
+    workdir: Pcchar;
+    hidden: cbool;
+    new_process_group: cbool;
+    new_console: cbool;
+    detached: cbool;
+    inherit_handles: cbool;
+    kill_tree: cbool;
+    kill_with_parent: cbool;
+    stdout: PDU_REDIRECT;
+    stderr: PDU_REDIRECT;
+    credentials: PDU_CREDENTIALS;
+    limits: PDU_LIMITS;
+  end;
+  PDU_SPAWN_OPTIONS = ^DU_SPAWN_OPTIONS;
+
+  DU_EXIT_CALLBACK = procedure(cls: Pcvoid; pid: cuint;
+    status: DU_EXIT_STATUS); cdecl;
+
+type
+  DU_ELEVATION = cenum;
+const
+  DU_ELEVATION_PKEXEC = 0;
+  DU_ELEVATION_SUDO = 1;
+  DU_ELEVATION_CUSTOM = 2;
+
+type
+  DU_STREAM = cenum;
+const
+  DU_STREAM_STDOUT = 0;
+  DU_STREAM_STDERR = 1;
+
+type
+  DU_STREAM_CALLBACK = function(cls: Pcvoid; stream: DU_STREAM;
+    const data: Pcvoid; size: csize_t): cbool; cdecl;
+
+  DU_PIPELINE_STAGE = record
+    &program: Pcchar;
+    args: PPcchar;
+    envs: PPcchar;
+    env_options: PDU_ENV_OPTIONS;
+    options: PDU_SPAWN_OPTIONS;
+  end;
+  PDU_PIPELINE_STAGE = ^DU_PIPELINE_STAGE;
+
+  PDU_PROCESS = Pointer;
+  PPDU_PROCESS = ^PDU_PROCESS;
+
 var
   du_version: function: Pcchar; cdecl;
   du_dispose: procedure(cstr: Pcchar); cdecl;
+  du_dispose_bytes: procedure(data: Pcvoid; size: csize_t); cdecl;
   du_md5: function(const cstr: Pcchar; md5: Pcchar; size: csize_t): cint; cdecl;
   du_md5_file: function(const filename: Pcchar; md5: Pcchar;
     size: csize_t): cint; cdecl;
@@ -114,6 +242,100 @@ var
   du_lockkey_state: function(key: DU_LOCKKEY): cbool; cdecl;
   du_terminate: function(const process_name: Pcchar): cint; cdecl;
   du_deltree: function(const pattern: Pcchar): cint; cdecl;
+  du_base64_encode: function(const data: Pcvoid; size: csize_t; urlsafe: cbool;
+    padding: cbool; output: PPcchar): cint; cdecl;
+  du_base64_decode: function(const cstr: Pcchar; urlsafe: cbool;
+    output: PPcvoid; size: Pcsize_t): cint; cdecl;
+  du_base32_encode: function(const data: Pcvoid; size: csize_t; padding: cbool;
+    output: PPcchar): cint; cdecl;
+  du_base32_decode: function(const cstr: Pcchar; output: PPcvoid;
+    size: Pcsize_t): cint; cdecl;
+  du_hex_encode: function(const data: Pcvoid; size: csize_t;
+    output: PPcchar): cint; cdecl;
+  du_hex_decode: function(const cstr: Pcchar; output: PPcvoid;
+    size: Pcsize_t): cint; cdecl;
+  du_hotp: function(const secret: Pcvoid; secret_size: csize_t; counter: cuint64;
+    digits: cint; algorithm: DU_OTP_ALGORITHM; code: Pcchar;
+    size: csize_t): cint; cdecl;
+  du_hotp_verify: function(const secret: Pcvoid; secret_size: csize_t;
+    counter: cuint64; window: cuint; digits: cint; algorithm: DU_OTP_ALGORITHM;
+    const code: Pcchar; matched: Pcuint64): cint; cdecl;
+  du_totp: function(const secret: Pcvoid; secret_size: csize_t;
+    timestamp: cuint64; period: cuint; digits: cint;
+    algorithm: DU_OTP_ALGORITHM; code: Pcchar; size: csize_t): cint; cdecl;
+  du_totp_verify: function(const secret: Pcvoid; secret_size: csize_t;
+    timestamp: cuint64; period: cuint; window: cuint; digits: cint;
+    algorithm: DU_OTP_ALGORITHM; const code: Pcchar): cint; cdecl;
+  du_totp_uri: function(const secret: Pcvoid; secret_size: csize_t;
+    const account: Pcchar; const issuer: Pcchar; period: cuint; digits: cint;
+    algorithm: DU_OTP_ALGORITHM; output: PPcchar): cint; cdecl;
+  du_hotp_uri: function(const secret: Pcvoid; secret_size: csize_t;
+    const account: Pcchar; const issuer: Pcchar; counter: cuint64; digits: cint;
+    algorithm: DU_OTP_ALGORITHM; output: PPcchar): cint; cdecl;
+  du_deltree_secure: function(const pattern: Pcchar; passes: cuint;
+    files: Pcuint64; bytes: Pcuint64): cint; cdecl;
+  du_certificate_info: function(const filename: Pcchar; const password: Pcchar;
+    subject: PPcchar; issuer: PPcchar; serial: PPcchar; not_before: Pcint64;
+    not_after: Pcint64; document: PPcchar): cint; cdecl;
+  du_xml_sign: function(const xml: Pcchar; const tag: Pcchar;
+    const filename: Pcchar; const password: Pcchar; output: PPcchar): cint; cdecl;
+  du_xml_verify: function(const xml: Pcchar): cint; cdecl;
+  du_process_start: function(const &program: Pcchar; const workdir: Pcchar;
+    const args: PPcchar; const envs: PPcchar;
+    const env_options: PDU_ENV_OPTIONS; handle: PPDU_PROCESS;
+    pid: Pcuint): cint; cdecl;
+  du_process_wait: function(handle: PDU_PROCESS; timeout: cuint;
+    status: PDU_EXIT_STATUS): cint; cdecl;
+  du_process_is_running: function(handle: PDU_PROCESS): cbool; cdecl;
+  du_process_kill: function(handle: PDU_PROCESS): cint; cdecl;
+  du_process_free: procedure(handle: PDU_PROCESS); cdecl;
+  du_execute_stream: function(const &program: Pcchar; const args: PPcchar;
+    const envs: PPcchar; const env_options: PDU_ENV_OPTIONS;
+    const options: PDU_SPAWN_OPTIONS; const input: Pcvoid;
+    input_size: csize_t; timeout: cuint; callback: DU_STREAM_CALLBACK;
+    cls: Pcvoid; status: PDU_EXIT_STATUS): cint; cdecl;
+  du_shell_execute: function(const command: Pcchar; const workdir: Pcchar;
+    const envs: PPcchar; const env_options: PDU_ENV_OPTIONS;
+    const input: Pcvoid; input_size: csize_t; timeout: cuint;
+    max_size: csize_t; overflow: DU_OVERFLOW; const encoding: Pcchar;
+    output: PPcvoid; output_size: Pcsize_t; error: PPcvoid;
+    error_size: Pcsize_t; truncated: Pcbool;
+    status: PDU_EXIT_STATUS): cint; cdecl;
+  du_shell_quote: function(const args: PPcchar; output: PPcchar): cint; cdecl;
+  du_pipeline_execute: function(const stages: PDU_PIPELINE_STAGE;
+    count: csize_t; const input: Pcvoid; input_size: csize_t; timeout: cuint;
+    output: PPcvoid; output_size: Pcsize_t; error: PPcvoid;
+    error_size: Pcsize_t; statuses: PDU_EXIT_STATUS): cint; cdecl;
+  du_spawn_detached: function(const &program: Pcchar; const workdir: Pcchar;
+    const args: PPcchar; const envs: PPcchar;
+    const env_options: PDU_ENV_OPTIONS; pid: Pcuint): cint; cdecl;
+  du_execute_pty: function(const &program: Pcchar; const workdir: Pcchar;
+    const args: PPcchar; const envs: PPcchar;
+    const env_options: PDU_ENV_OPTIONS; const input: Pcvoid;
+    input_size: csize_t; timeout: cuint; rows: cushort; cols: cushort;
+    output: PPcvoid; output_size: Pcsize_t;
+    status: PDU_EXIT_STATUS): cint; cdecl;
+  du_spawn_ex: function(const &program: Pcchar; const args: PPcchar;
+    const envs: PPcchar; const env_options: PDU_ENV_OPTIONS;
+    const options: PDU_SPAWN_OPTIONS; waiting: cbool; timeout: cuint;
+    callback: DU_EXIT_CALLBACK; cls: Pcvoid;
+    status: PDU_EXIT_STATUS): cint; cdecl;
+  du_execute_ex: function(const &program: Pcchar; const args: PPcchar;
+    const envs: PPcchar; const env_options: PDU_ENV_OPTIONS;
+    const options: PDU_SPAWN_OPTIONS; const input: Pcvoid;
+    input_size: csize_t; timeout: cuint; max_size: csize_t;
+    overflow: DU_OVERFLOW; const encoding: Pcchar; output: PPcvoid;
+    output_size: Pcsize_t; error: PPcvoid; error_size: Pcsize_t;
+    truncated: Pcbool; status: PDU_EXIT_STATUS): cint; cdecl;
+  du_process_start_ex: function(const &program: Pcchar; const args: PPcchar;
+    const envs: PPcchar; const env_options: PDU_ENV_OPTIONS;
+    const options: PDU_SPAWN_OPTIONS; handle: PPDU_PROCESS;
+    pid: Pcuint): cint; cdecl;
+  du_spawn_elevated: function(const &program: Pcchar; const workdir: Pcchar;
+    const args: PPcchar; const envs: PPcchar;
+    const env_options: PDU_ENV_OPTIONS; helper: DU_ELEVATION;
+    const helper_path: Pcchar; timeout: cuint;
+    status: PDU_EXIT_STATUS): cint; cdecl;
 
 function TryLoad(const ALibraryName: TFileName): Boolean;
 
@@ -144,6 +366,7 @@ begin
     GLibLastName := ALibraryName;
     du_version := GetProcAddress(GLibHandle, 'du_version');
     du_dispose := GetProcAddress(GLibHandle, 'du_dispose');
+    du_dispose_bytes := GetProcAddress(GLibHandle, 'du_dispose_bytes');
     du_md5 := GetProcAddress(GLibHandle, 'du_md5');
     du_md5_file := GetProcAddress(GLibHandle, 'du_md5_file');
     du_sha1 := GetProcAddress(GLibHandle, 'du_sha1');
@@ -160,6 +383,37 @@ begin
     du_datetime_set := GetProcAddress(GLibHandle, 'du_datetime_set');
     du_terminate := GetProcAddress(GLibHandle, 'du_terminate');
     du_deltree := GetProcAddress(GLibHandle, 'du_deltree');
+    du_base64_encode := GetProcAddress(GLibHandle, 'du_base64_encode');
+    du_base64_decode := GetProcAddress(GLibHandle, 'du_base64_decode');
+    du_base32_encode := GetProcAddress(GLibHandle, 'du_base32_encode');
+    du_base32_decode := GetProcAddress(GLibHandle, 'du_base32_decode');
+    du_hex_encode := GetProcAddress(GLibHandle, 'du_hex_encode');
+    du_hex_decode := GetProcAddress(GLibHandle, 'du_hex_decode');
+    du_hotp := GetProcAddress(GLibHandle, 'du_hotp');
+    du_hotp_verify := GetProcAddress(GLibHandle, 'du_hotp_verify');
+    du_totp := GetProcAddress(GLibHandle, 'du_totp');
+    du_totp_verify := GetProcAddress(GLibHandle, 'du_totp_verify');
+    du_totp_uri := GetProcAddress(GLibHandle, 'du_totp_uri');
+    du_hotp_uri := GetProcAddress(GLibHandle, 'du_hotp_uri');
+    du_deltree_secure := GetProcAddress(GLibHandle, 'du_deltree_secure');
+    du_certificate_info := GetProcAddress(GLibHandle, 'du_certificate_info');
+    du_xml_sign := GetProcAddress(GLibHandle, 'du_xml_sign');
+    du_xml_verify := GetProcAddress(GLibHandle, 'du_xml_verify');
+    du_process_start := GetProcAddress(GLibHandle, 'du_process_start');
+    du_process_wait := GetProcAddress(GLibHandle, 'du_process_wait');
+    du_process_is_running := GetProcAddress(GLibHandle, 'du_process_is_running');
+    du_process_kill := GetProcAddress(GLibHandle, 'du_process_kill');
+    du_process_free := GetProcAddress(GLibHandle, 'du_process_free');
+    du_execute_stream := GetProcAddress(GLibHandle, 'du_execute_stream');
+    du_shell_execute := GetProcAddress(GLibHandle, 'du_shell_execute');
+    du_shell_quote := GetProcAddress(GLibHandle, 'du_shell_quote');
+    du_pipeline_execute := GetProcAddress(GLibHandle, 'du_pipeline_execute');
+    du_spawn_detached := GetProcAddress(GLibHandle, 'du_spawn_detached');
+    du_execute_pty := GetProcAddress(GLibHandle, 'du_execute_pty');
+    du_spawn_ex := GetProcAddress(GLibHandle, 'du_spawn_ex');
+    du_execute_ex := GetProcAddress(GLibHandle, 'du_execute_ex');
+    du_process_start_ex := GetProcAddress(GLibHandle, 'du_process_start_ex');
+    du_spawn_elevated := GetProcAddress(GLibHandle, 'du_spawn_elevated');
     Result := True;
   finally
     GCS.Release;
@@ -188,6 +442,7 @@ begin
     GLibLastName := '';
     du_version := nil;
     du_dispose := nil;
+    du_dispose_bytes := nil;
     du_md5 := nil;
     du_md5_file := nil;
     du_sha1 := nil;
@@ -204,6 +459,37 @@ begin
     du_datetime_set := nil;
     du_terminate := nil;
     du_deltree := nil;
+    du_base64_encode := nil;
+    du_base64_decode := nil;
+    du_base32_encode := nil;
+    du_base32_decode := nil;
+    du_hex_encode := nil;
+    du_hex_decode := nil;
+    du_hotp := nil;
+    du_hotp_verify := nil;
+    du_totp := nil;
+    du_totp_verify := nil;
+    du_totp_uri := nil;
+    du_hotp_uri := nil;
+    du_deltree_secure := nil;
+    du_certificate_info := nil;
+    du_xml_sign := nil;
+    du_xml_verify := nil;
+    du_process_start := nil;
+    du_process_wait := nil;
+    du_process_is_running := nil;
+    du_process_kill := nil;
+    du_process_free := nil;
+    du_execute_stream := nil;
+    du_shell_execute := nil;
+    du_shell_quote := nil;
+    du_pipeline_execute := nil;
+    du_spawn_detached := nil;
+    du_execute_pty := nil;
+    du_spawn_ex := nil;
+    du_execute_ex := nil;
+    du_process_start_ex := nil;
+    du_spawn_elevated := nil;
   finally
     GCS.Release;
   end;
@@ -5,4 +5,4 @@
 
 set -e
 
-RUSTFLAGS="-C link-arg=-s" cargo build --release
+RUSTFLAGS="-C link-arg=-s" cargo build --release --features legacy-provider
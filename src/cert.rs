@@ -0,0 +1,82 @@
+use libc::c_int;
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use openssl::error::ErrorStack;
+use openssl::nid::Nid;
+use openssl::pkcs12::{ParsedPkcs12_2, Pkcs12};
+#[cfg(feature = "legacy-provider")]
+use openssl::provider::Provider;
+use openssl::x509::X509NameRef;
+use std::fs;
+use std::io::ErrorKind::NotFound;
+#[cfg(feature = "legacy-provider")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "legacy-provider")]
+static LEGACY: OnceLock<Option<Provider>> = OnceLock::new();
+
+/// A1 certificates are commonly encrypted with RC2, which OpenSSL 3 moved into the legacy
+/// provider. Requires the `legacy-provider` feature, since providers are OpenSSL 3 only.
+#[cfg(feature = "legacy-provider")]
+fn load_legacy_provider() {
+    LEGACY.get_or_init(|| Provider::try_load(None, "legacy", true).ok());
+}
+
+#[cfg(not(feature = "legacy-provider"))]
+fn load_legacy_provider() {}
+
+pub fn load_pkcs12(filename: &str, password: &str) -> Result<ParsedPkcs12_2, c_int> {
+    load_legacy_provider();
+    let der = match fs::read(filename) {
+        Ok(der) => der,
+        Err(error) => {
+            if error.kind() == NotFound {
+                return Err(-2);
+            }
+            return Err(-5);
+        }
+    };
+    let pkcs12 = Pkcs12::from_der(&der).map_err(|_| -4)?;
+    pkcs12
+        .parse2(password)
+        .map_err(|error| if is_mac_failure(&error) { -3 } else { -4 })
+}
+
+// From OpenSSL's `err.h` and `pkcs12err.h`, which `openssl-sys` doesn't export.
+const ERR_LIB_PKCS12: c_int = 35;
+const PKCS12_R_MAC_VERIFY_FAILURE: c_int = 113;
+
+fn is_mac_failure(error: &ErrorStack) -> bool {
+    error.errors().iter().any(|e| {
+        e.library_code() == ERR_LIB_PKCS12 && e.reason_code() == PKCS12_R_MAC_VERIFY_FAILURE
+    })
+}
+
+pub fn name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or_default();
+            let value = entry.data().to_string().unwrap_or_default();
+            format!("{}={}", key, value)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Extracts the CNPJ or CPF from an ICP-Brasil common name, e.g. `COMPANY NAME:12345678000195`.
+pub fn document(name: &X509NameRef) -> Option<String> {
+    let cn = name.entries_by_nid(Nid::COMMONNAME).next()?;
+    let cn = cn.data().to_string().ok()?;
+    let document = cn.rsplit(':').next()?;
+    if (document.len() == 11 || document.len() == 14)
+        && document.bytes().all(|c| c.is_ascii_digit())
+    {
+        Some(document.to_string())
+    } else {
+        None
+    }
+}
+
+pub fn to_unix_time(time: &Asn1TimeRef) -> Option<i64> {
+    let diff = Asn1Time::from_unix(0).ok()?.diff(time).ok()?;
+    Some(i64::from(diff.days) * 86400 + i64::from(diff.secs))
+}
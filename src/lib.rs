@@ -1,3 +1,5 @@
+#![allow(clippy::missing_safety_doc)]
+
 #[cfg(target_os = "windows")]
 extern crate winapi;
 
@@ -12,23 +14,34 @@ mod os;
 #[path = "windows.rs"]
 mod os;
 
+use charset::Charset;
 use crypto::digest::Digest;
 use crypto::md5::Md5;
 use crypto::sha1::Sha1;
+use data_encoding::{
+    BASE32, BASE32_NOPAD, BASE64, BASE64URL, BASE64URL_NOPAD, BASE64_NOPAD, HEXLOWER,
+    HEXLOWER_PERMISSIVE,
+};
 use glob::glob;
-use libc::{c_char, c_int, size_t};
+use libc::{c_char, c_int, c_uint, c_ushort, c_void, size_t};
 use lock_keys::{LockKey, LockKeyWrapper};
-use opener;
 use single_instance::SingleInstance;
-use std::ffi::CString;
-use std::fs::{self, File};
-use std::io::ErrorKind::NotFound;
-use std::io::Read;
-use std::process::{Command, Stdio};
+use std::ffi::{CStr, CString};
+use std::fs::{self, File, OpenOptions};
+use std::io::ErrorKind::{NotFound, Unsupported};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
 use std::ptr;
 use system_shutdown::{force_logout, force_reboot, force_shutdown, logout, reboot, shutdown};
 
+mod cert;
+mod charset;
+mod otp;
+mod process;
+mod shell;
 mod utils;
+mod xmldsig;
 
 #[cfg(target_os = "windows")]
 const BUFFER_SIZE: usize = 4096; /* 4k */
@@ -49,7 +62,22 @@ pub unsafe extern "C" fn du_version() -> *const c_char {
 #[no_mangle]
 pub unsafe extern "C" fn du_dispose(cstr: *mut c_char) {
     if !cstr.is_null() {
-        CString::from_raw(cstr);
+        drop(CString::from_raw(cstr));
+    }
+}
+
+/// Frees the bytes buffer `data` from the memory.
+///
+/// # Arguments
+///
+/// * `[in] data` - Bytes buffer to be freed.
+/// * `[in] size` - Size of the `data` buffer.
+#[no_mangle]
+pub unsafe extern "C" fn du_dispose_bytes(data: *mut u8, size: size_t) {
+    if !data.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            data, size,
+        )));
     }
 }
 
@@ -67,7 +95,7 @@ pub unsafe extern "C" fn du_dispose(cstr: *mut c_char) {
 /// * `-1` - Invalid argument.
 #[no_mangle]
 pub unsafe extern "C" fn du_md5(cstr: *const c_char, md5: *mut c_char, size: size_t) -> c_int {
-    if cstr.is_null() || md5.is_null() || size <= 0 {
+    if cstr.is_null() || md5.is_null() || size == 0 {
         return -1;
     }
     let mut hasher = Md5::new();
@@ -97,7 +125,7 @@ pub unsafe extern "C" fn du_md5_file(
     md5: *mut c_char,
     size: size_t,
 ) -> c_int {
-    if filename.is_null() || md5.is_null() || size <= 0 {
+    if filename.is_null() || md5.is_null() || size == 0 {
         return -1;
     }
     match File::open(from_c_str!(filename).unwrap()) {
@@ -141,7 +169,7 @@ pub unsafe extern "C" fn du_md5_file(
 /// * `-1` - Invalid argument.
 #[no_mangle]
 pub unsafe extern "C" fn du_sha1(cstr: *const c_char, sha1: *mut c_char, size: size_t) -> c_int {
-    if cstr.is_null() || sha1.is_null() || size <= 0 {
+    if cstr.is_null() || sha1.is_null() || size == 0 {
         return -1;
     }
     let mut hasher = Sha1::new();
@@ -171,7 +199,7 @@ pub unsafe extern "C" fn du_sha1_file(
     sha1: *mut c_char,
     size: size_t,
 ) -> c_int {
-    if filename.is_null() || sha1.is_null() || size <= 0 {
+    if filename.is_null() || sha1.is_null() || size == 0 {
         return -1;
     }
     match File::open(from_c_str!(filename).unwrap()) {
@@ -201,687 +229,5606 @@ pub unsafe extern "C" fn du_sha1_file(
     0
 }
 
-/// Executes the command as a child process.
+/// Encodes a given data to Base64.
 ///
 /// # Arguments
 ///
-/// * `[in] program` - Program path as C-like string.
-/// * `[in] workdir` - Working directory as C-like string.
-/// * `[in] args` - Arguments to pass to the program as array of C-like string.
-/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
-/// * `[in] waiting` - Waiting for the program to exit completely.
-/// * `[in,out] exitcode` - Exit code of the process.
+/// * `[in] data` - Data to be encoded.
+/// * `[in] size` - Size of the `data`.
+/// * `[in] urlsafe` - Uses the URL and filename safe alphabet.
+/// * `[in] padding` - Appends the `=` padding characters.
+/// * `[in,out] output` - Encoded data as C-like string.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - Program not found.
-/// * `-3` - Unknown error.
 #[no_mangle]
-pub unsafe extern "C" fn du_spawn(
-    program: *const c_char,
-    workdir: *const c_char,
-    args: *const *const c_char,
-    envs: *const *const c_char,
-    waiting: bool,
-    exitcode: *mut c_int,
+pub unsafe extern "C" fn du_base64_encode(
+    data: *const u8,
+    size: size_t,
+    urlsafe: bool,
+    padding: bool,
+    output: *mut *mut c_char,
 ) -> c_int {
-    if program.is_null() {
+    if data.is_null() || output.is_null() {
         return -1;
     }
-    let mut cmd = Command::new(from_c_str!(program).unwrap());
-    if !workdir.is_null() {
-        cmd.current_dir(from_c_str!(workdir).unwrap());
-    }
-    if cfg!(test) {
-        cmd.stdout(Stdio::null());
-    }
-    if !args.is_null() {
-        cmd.args(&from_c_array!(args));
-    }
-    if !envs.is_null() {
-        for i in 0.. {
-            let env = *(envs.offset(i));
-            if env == ptr::null() {
-                break;
-            }
-            let pair: Vec<&str> = from_c_str!(env).unwrap().splitn(2, "=").collect();
-            if pair.len() == 2 {
-                cmd.env(pair.first().unwrap(), pair.last().unwrap());
-            }
-        }
-    }
-    match cmd.spawn() {
-        Ok(mut child) => {
-            if waiting {
-                match child.try_wait() {
-                    Ok(Some(status)) => *exitcode = status.code().unwrap(),
-                    Ok(None) => *exitcode = child.wait().unwrap().code().unwrap(),
-                    Err(_) => return -3,
-                }
-            }
-        }
-        Err(error) => {
-            if error.kind() == NotFound {
-                return -2;
-            }
-            return -3;
-        }
-    }
+    let encoding = match (urlsafe, padding) {
+        (false, true) => &BASE64,
+        (false, false) => &BASE64_NOPAD,
+        (true, true) => &BASE64URL,
+        (true, false) => &BASE64URL_NOPAD,
+    };
+    let encoded = encoding.encode(std::slice::from_raw_parts(data, size));
+    *output = to_c_str!(encoded).unwrap().into_raw();
     0
 }
 
-/// Executes the command as a child process waiting for it to finish and collecting all of its output.
+/// Decodes a given Base64 string, padded or not.
 ///
 /// # Arguments
 ///
-/// * `[in] program` - Program path as C-like string.
-/// * `[in] workdir` - Working directory as C-like string.
-/// * `[in] args` - Arguments to pass to the program as array of C-like string.
-/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
-/// * `[in,out] output` - C-like string containing the `stdout` content if it exists.
-/// * `[in,out] error` - C-like string containing the `stderr` content if it exists.
-/// * `[in,out] exitcode` - Exit code of the process.
+/// * `[in] cstr` - Base64 as C-like string.
+/// * `[in] urlsafe` - Uses the URL and filename safe alphabet.
+/// * `[in,out] output` - Decoded data.
+/// * `[in,out] size` - Size of the `output` data.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - Program not found.
-/// * `-3` - Unknown error.
+/// * `-2` - Invalid Base64.
 #[no_mangle]
-pub unsafe extern "C" fn du_execute(
-    program: *const c_char,
-    workdir: *const c_char,
-    args: *const *const c_char,
-    envs: *const *const c_char,
-    output: *mut *mut c_char,
-    error: *mut *mut c_char,
-    exitcode: *mut c_int,
+pub unsafe extern "C" fn du_base64_decode(
+    cstr: *const c_char,
+    urlsafe: bool,
+    output: *mut *mut u8,
+    size: *mut size_t,
 ) -> c_int {
-    if program.is_null() {
+    if cstr.is_null() || output.is_null() || size.is_null() {
         return -1;
     }
-    let mut cmd = Command::new(from_c_str!(program).unwrap());
-    if !workdir.is_null() {
-        cmd.current_dir(from_c_str!(workdir).unwrap());
-    }
-    if !args.is_null() {
-        cmd.args(&from_c_array!(args));
-    }
-    if !envs.is_null() {
-        for i in 0.. {
-            let env = *(envs.offset(i));
-            if env == ptr::null() {
-                break;
-            }
-            let pair: Vec<&str> = from_c_str!(env).unwrap().splitn(2, "=").collect();
-            if pair.len() == 2 {
-                cmd.env(pair.first().unwrap(), pair.last().unwrap());
-            }
-        }
-    }
-    match cmd.output() {
-        Ok(child) => {
-            *output = to_c_str!(String::from_utf8(child.stdout).unwrap())
-                .unwrap()
-                .into_raw();
-            *error = to_c_str!(String::from_utf8(child.stderr).unwrap())
-                .unwrap()
-                .into_raw();
-            *exitcode = child.status.code().unwrap();
-        }
-        Err(error) => {
-            if error.kind() == NotFound {
-                return -2;
-            }
-            return -3;
+    let encoded = CStr::from_ptr(cstr).to_bytes();
+    let encoding = match (urlsafe, padded(encoded)) {
+        (false, true) => &BASE64,
+        (false, false) => &BASE64_NOPAD,
+        (true, true) => &BASE64URL,
+        (true, false) => &BASE64URL_NOPAD,
+    };
+    match encoding.decode(encoded) {
+        Ok(decoded) => {
+            into_raw_bytes!(decoded, output, size);
+            0
         }
+        Err(_) => -2,
     }
-    0
 }
 
-/// Opens a file or link with the system default program.
+/// Encodes a given data to Base32.
 ///
 /// # Arguments
 ///
-/// * `[in] path` - File or link as C-like string.
+/// * `[in] data` - Data to be encoded.
+/// * `[in] size` - Size of the `data`.
+/// * `[in] padding` - Appends the `=` padding characters.
+/// * `[in,out] output` - Encoded data as C-like string.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - Unknown error.
 #[no_mangle]
-pub unsafe extern "C" fn du_open(path: *const c_char) -> c_int {
-    if path.is_null() {
+pub unsafe extern "C" fn du_base32_encode(
+    data: *const u8,
+    size: size_t,
+    padding: bool,
+    output: *mut *mut c_char,
+) -> c_int {
+    if data.is_null() || output.is_null() {
         return -1;
     }
-    match opener::open(from_c_str!(path).unwrap()) {
-        Ok(_) => 0,
-        Err(_) => -2,
-    }
+    let encoding = if padding { &BASE32 } else { &BASE32_NOPAD };
+    let encoded = encoding.encode(std::slice::from_raw_parts(data, size));
+    *output = to_c_str!(encoded).unwrap().into_raw();
+    0
 }
 
-/// Checks if there are any other running instance.
+/// Decodes a given Base32 string, padded or not.
 ///
 /// # Arguments
 ///
-/// * `[in] identifier` - Unique path/name as C-like string to identify the instance.
+/// * `[in] cstr` - Base32 as C-like string.
+/// * `[in,out] output` - Decoded data.
+/// * `[in,out] size` - Size of the `output` data.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - Already running.
-/// * `-3` - Unknown error.
+/// * `-2` - Invalid Base32.
 #[no_mangle]
-pub unsafe extern "C" fn du_once(identifier: *const c_char) -> c_int {
-    if identifier.is_null() {
+pub unsafe extern "C" fn du_base32_decode(
+    cstr: *const c_char,
+    output: *mut *mut u8,
+    size: *mut size_t,
+) -> c_int {
+    if cstr.is_null() || output.is_null() || size.is_null() {
         return -1;
     }
-    match SingleInstance::new(from_c_str!(identifier).unwrap()) {
-        Ok(instance) => {
-            static mut RUNNING_INSTANCE: Option<SingleInstance> = None;
-            RUNNING_INSTANCE = Some(instance);
-            match RUNNING_INSTANCE {
-                Some(ref instance) => {
-                    if instance.is_single() {
-                        0
-                    } else {
-                        -2
-                    }
-                }
-                None => -3,
-            }
+    let encoded = CStr::from_ptr(cstr).to_bytes();
+    let encoding = if padded(encoded) {
+        &BASE32
+    } else {
+        &BASE32_NOPAD
+    };
+    match encoding.decode(encoded) {
+        Ok(decoded) => {
+            into_raw_bytes!(decoded, output, size);
+            0
         }
-        Err(_) => -3,
+        Err(_) => -2,
     }
 }
 
-/// Calls the OS-specific function to shut down the machine.
+/// Encodes a given data to lowercase hexadecimal.
 ///
 /// # Arguments
 ///
-/// * `[in] forced` - Forces the machine to shut down instantly without confirmations.
-/// * `[in,out] error_msg` - Error message as C-like string returned by the OS when the function fails.
-/// * `[in] error_size` - Size of the error message.
+/// * `[in] data` - Data to be encoded.
+/// * `[in] size` - Size of the `data`.
+/// * `[in,out] output` - Encoded data as C-like string.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - OS error.
 #[no_mangle]
-pub unsafe extern "C" fn du_shutdown(
-    forced: bool,
-    error_msg: *mut c_char,
-    error_size: size_t,
+pub unsafe extern "C" fn du_hex_encode(
+    data: *const u8,
+    size: size_t,
+    output: *mut *mut c_char,
 ) -> c_int {
-    if error_msg.is_null() || error_size <= 0 {
+    if data.is_null() || output.is_null() {
         return -1;
     }
-    let result = if forced { force_shutdown() } else { shutdown() };
-    match result {
-        Ok(_) => 0,
-        Err(error) => {
-            let msg = to_c_str!(error.to_string()).unwrap();
-            copy_c_str!(msg, error_msg, error_size);
-            -2
-        }
-    }
+    let encoded = HEXLOWER.encode(std::slice::from_raw_parts(data, size));
+    *output = to_c_str!(encoded).unwrap().into_raw();
+    0
 }
 
-/// Calls the OS-specific function to reboot the machine.
+/// Decodes a given hexadecimal string, lowercase or uppercase.
 ///
 /// # Arguments
 ///
-/// * `[in] forced` - Forces the machine to reboot instantly without confirmations.
-/// * `[in,out] error_msg` - Error message as C-like string returned by the OS when the function fails.
-/// * `[in] error_size` - Size of the error message.
+/// * `[in] cstr` - Hexadecimal as C-like string.
+/// * `[in,out] output` - Decoded data.
+/// * `[in,out] size` - Size of the `output` data.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - OS error.
+/// * `-2` - Invalid hexadecimal.
 #[no_mangle]
-pub unsafe extern "C" fn du_reboot(
-    forced: bool,
-    error_msg: *mut c_char,
-    error_size: size_t,
+pub unsafe extern "C" fn du_hex_decode(
+    cstr: *const c_char,
+    output: *mut *mut u8,
+    size: *mut size_t,
 ) -> c_int {
-    if error_msg.is_null() || error_size <= 0 {
+    if cstr.is_null() || output.is_null() || size.is_null() {
         return -1;
     }
-    let result = if forced { force_reboot() } else { reboot() };
-    match result {
-        Ok(_) => 0,
-        Err(error) => {
-            let msg = to_c_str!(error.to_string()).unwrap();
-            copy_c_str!(msg, error_msg, error_size);
-            -2
+    match HEXLOWER_PERMISSIVE.decode(CStr::from_ptr(cstr).to_bytes()) {
+        Ok(decoded) => {
+            into_raw_bytes!(decoded, output, size);
+            0
         }
+        Err(_) => -2,
     }
 }
 
-/// Calls the OS-specific function to log out the user.
+/// Checks if the encoded data ends with padding, so it is decoded requiring the exact padding
+/// length.
+fn padded(encoded: &[u8]) -> bool {
+    encoded.last() == Some(&b'=')
+}
+
+/// Available HMAC algorithms for one-time password generation.
+#[repr(C)]
+pub enum DU_OTP_ALGORITHM {
+    #[allow(non_camel_case_types)]
+    DU_OTP_SHA1,
+    #[allow(non_camel_case_types)]
+    DU_OTP_SHA256,
+}
+
+/// Generates a HMAC-based one-time password (HOTP) as specified in RFC 4226.
 ///
 /// # Arguments
 ///
-/// * `[in] forced` - Forces the machine to log out instantly without confirmations.
-/// * `[in,out] error_msg` - Error message as C-like string returned by the OS when the function fails.
-/// * `[in] error_size` - Size of the error message.
+/// * `[in] secret` - Shared secret.
+/// * `[in] secret_size` - Size of the `secret`.
+/// * `[in] counter` - Moving factor shared between client and server.
+/// * `[in] digits` - Number of digits of the code, from `6` to `8`.
+/// * `[in] algorithm` - HMAC algorithm.
+/// * `[in,out] code` - Generated code as C-like string.
+/// * `[in] size` - Size of the `code` string.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - OS error.
 #[no_mangle]
-pub unsafe extern "C" fn du_logout(
-    forced: bool,
-    error_msg: *mut c_char,
-    error_size: size_t,
+pub unsafe extern "C" fn du_hotp(
+    secret: *const u8,
+    secret_size: size_t,
+    counter: u64,
+    digits: c_int,
+    algorithm: DU_OTP_ALGORITHM,
+    code: *mut c_char,
+    size: size_t,
 ) -> c_int {
-    if error_msg.is_null() || error_size <= 0 {
+    if secret.is_null() || !(6..=8).contains(&digits) || code.is_null() || size == 0 {
         return -1;
     }
-    let result = if forced { force_logout() } else { logout() };
-    match result {
-        Ok(_) => 0,
-        Err(error) => {
-            let msg = to_c_str!(error.to_string()).unwrap();
-            copy_c_str!(msg, error_msg, error_size);
-            -2
-        }
-    }
-}
-
-/// Available lock keys for handling, i.e. Capital Lock, Number Lock and Scrolling Lock.
-#[repr(C)]
-pub enum DU_LOCKKEY {
-    #[allow(non_camel_case_types)]
-    DU_LK_CAPSLOCK,
-    #[allow(non_camel_case_types)]
-    DU_LK_NUMLOCK,
-    #[allow(non_camel_case_types)]
-    DU_LK_SCROLLLOCK,
+    let secret = std::slice::from_raw_parts(secret, secret_size);
+    let hotp = to_c_str!(otp::hotp(secret, counter, digits as u32, &algorithm)).unwrap();
+    copy_c_str!(hotp, code, size);
+    0
 }
 
-/// Enables the lock key.
+/// Verifies a HMAC-based one-time password (HOTP) as specified in RFC 4226.
 ///
 /// # Arguments
 ///
-/// * `[in] key` - The key to set a new state.
-/// * `[in] enabled` - The new state (enabled/disabled) for the key.
+/// * `[in] secret` - Shared secret.
+/// * `[in] secret_size` - Size of the `secret`.
+/// * `[in] counter` - Moving factor shared between client and server.
+/// * `[in] window` - Number of counters after `counter` also accepted (look-ahead).
+/// * `[in] digits` - Number of digits of the code, from `6` to `8`.
+/// * `[in] algorithm` - HMAC algorithm.
+/// * `[in] code` - Code to be verified as C-like string.
+/// * `[in,out] matched` - Counter which matched the code (optional).
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Invalid code.
 #[no_mangle]
-pub unsafe extern "C" fn du_lockkey_set(key: DU_LOCKKEY, enabled: bool) {
-    let lockkey = LockKey::new();
-    lockkey.set(key.into(), enabled.into()).unwrap();
+pub unsafe extern "C" fn du_hotp_verify(
+    secret: *const u8,
+    secret_size: size_t,
+    counter: u64,
+    window: c_uint,
+    digits: c_int,
+    algorithm: DU_OTP_ALGORITHM,
+    code: *const c_char,
+    matched: *mut u64,
+) -> c_int {
+    if secret.is_null() || !(6..=8).contains(&digits) || code.is_null() {
+        return -1;
+    }
+    let secret = std::slice::from_raw_parts(secret, secret_size);
+    let code = from_c_str!(code).unwrap_or_default();
+    for i in 0..=u64::from(window) {
+        let current = match counter.checked_add(i) {
+            Some(current) => current,
+            None => break,
+        };
+        if otp::hotp_verify(secret, current, digits as u32, &algorithm, code) {
+            if !matched.is_null() {
+                *matched = current;
+            }
+            return 0;
+        }
+    }
+    -2
 }
 
-/// Retrieve the lock key state.
+/// Generates a time-based one-time password (TOTP) as specified in RFC 6238.
 ///
 /// # Arguments
 ///
-/// * `[in] key` - The key to set a new state.
+/// * `[in] secret` - Shared secret.
+/// * `[in] secret_size` - Size of the `secret`.
+/// * `[in] timestamp` - Unix time in seconds, or `0` for the current time.
+/// * `[in] period` - Time step in seconds, usually `30`.
+/// * `[in] digits` - Number of digits of the code, from `6` to `8`.
+/// * `[in] algorithm` - HMAC algorithm.
+/// * `[in,out] code` - Generated code as C-like string.
+/// * `[in] size` - Size of the `code` string.
 ///
 /// # Returns
 ///
-/// * `true` - Enabled.
-/// * `false` - Disabled.
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
 #[no_mangle]
-pub unsafe extern "C" fn du_lockkey_state(key: DU_LOCKKEY) -> bool {
-    let lockkey = LockKey::new();
-    lockkey.state(key.into()).unwrap().into()
+pub unsafe extern "C" fn du_totp(
+    secret: *const u8,
+    secret_size: size_t,
+    timestamp: u64,
+    period: c_uint,
+    digits: c_int,
+    algorithm: DU_OTP_ALGORITHM,
+    code: *mut c_char,
+    size: size_t,
+) -> c_int {
+    if period == 0 {
+        return -1;
+    }
+    let counter = otp::timestamp_or_now(timestamp) / u64::from(period);
+    du_hotp(secret, secret_size, counter, digits, algorithm, code, size)
 }
 
-/// Calls the OS-specific function to change the system date and time.
+/// Verifies a time-based one-time password (TOTP) as specified in RFC 6238.
 ///
 /// # Arguments
 ///
-/// * `[in] year` - The year to be changed.
-/// * `[in] month` - The month to be changed.
-/// * `[in] day` - The day to be changed.
-/// * `[in] hour` - The hour to be changed.
-/// * `[in] minute` - The minute to be changed.
-/// * `[in] second` - The second to be changed.
+/// * `[in] secret` - Shared secret.
+/// * `[in] secret_size` - Size of the `secret`.
+/// * `[in] timestamp` - Unix time in seconds, or `0` for the current time.
+/// * `[in] period` - Time step in seconds, usually `30`.
+/// * `[in] window` - Number of time steps before and after `timestamp` also accepted.
+/// * `[in] digits` - Number of digits of the code, from `6` to `8`.
+/// * `[in] algorithm` - HMAC algorithm.
+/// * `[in] code` - Code to be verified as C-like string.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - Operation not permitted.
-/// * `-3` - OS error.
+/// * `-2` - Invalid code.
 #[no_mangle]
-pub unsafe extern "C" fn du_datetime_set(
-    year: c_int,
-    month: c_int,
-    day: c_int,
-    hour: c_int,
-    minute: c_int,
-    second: c_int,
+pub unsafe extern "C" fn du_totp_verify(
+    secret: *const u8,
+    secret_size: size_t,
+    timestamp: u64,
+    period: c_uint,
+    window: c_uint,
+    digits: c_int,
+    algorithm: DU_OTP_ALGORITHM,
+    code: *const c_char,
 ) -> c_int {
-    os::datetime_set(year, month, day, hour, minute, second)
+    if period == 0 {
+        return -1;
+    }
+    let counter = otp::timestamp_or_now(timestamp) / u64::from(period);
+    let first = counter.saturating_sub(u64::from(window));
+    du_hotp_verify(
+        secret,
+        secret_size,
+        first,
+        window.saturating_add((counter - first) as c_uint),
+        digits,
+        algorithm,
+        code,
+        ptr::null_mut(),
+    )
 }
 
-/// Calls the OS-specific function to terminate a process.
+/// Generates an `otpauth://totp/` URI for enrolling a TOTP secret in authenticator apps.
 ///
 /// # Arguments
 ///
-/// * `[in] process_name` - The process name to be terminated.
+/// * `[in] secret` - Shared secret.
+/// * `[in] secret_size` - Size of the `secret`.
+/// * `[in] account` - Account name as C-like string.
+/// * `[in] issuer` - Issuer (company or service) as C-like string (optional).
+/// * `[in] period` - Time step in seconds, usually `30`.
+/// * `[in] digits` - Number of digits of the code, from `6` to `8`.
+/// * `[in] algorithm` - HMAC algorithm.
+/// * `[in,out] output` - Generated URI as C-like string.
 ///
 /// # Returns
 ///
 /// * `0` - Success.
 /// * `-1` - Invalid argument.
-/// * `-2` - No process found.
-/// * `-3` - Operation not permitted.
-/// * `-4` - OS error.
 #[no_mangle]
-pub unsafe extern "C" fn du_terminate(process_name: *const c_char) -> c_int {
-    if process_name.is_null() {
+pub unsafe extern "C" fn du_totp_uri(
+    secret: *const u8,
+    secret_size: size_t,
+    account: *const c_char,
+    issuer: *const c_char,
+    period: c_uint,
+    digits: c_int,
+    algorithm: DU_OTP_ALGORITHM,
+    output: *mut *mut c_char,
+) -> c_int {
+    if secret.is_null()
+        || account.is_null()
+        || period == 0
+        || !(6..=8).contains(&digits)
+        || output.is_null()
+    {
+        return -1;
+    }
+    let issuer = if issuer.is_null() {
+        None
+    } else {
+        Some(from_c_str!(issuer).unwrap())
+    };
+    let uri = otp::uri(
+        "totp",
+        std::slice::from_raw_parts(secret, secret_size),
+        from_c_str!(account).unwrap(),
+        issuer,
+        digits as u32,
+        &algorithm,
+        ("period", u64::from(period)),
+    );
+    *output = to_c_str!(uri).unwrap().into_raw();
+    0
+}
+
+/// Generates an `otpauth://hotp/` URI for enrolling a HOTP secret in authenticator apps.
+///
+/// # Arguments
+///
+/// * `[in] secret` - Shared secret.
+/// * `[in] secret_size` - Size of the `secret`.
+/// * `[in] account` - Account name as C-like string.
+/// * `[in] issuer` - Issuer (company or service) as C-like string (optional).
+/// * `[in] counter` - Initial counter.
+/// * `[in] digits` - Number of digits of the code, from `6` to `8`.
+/// * `[in] algorithm` - HMAC algorithm.
+/// * `[in,out] output` - Generated URI as C-like string.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+#[no_mangle]
+pub unsafe extern "C" fn du_hotp_uri(
+    secret: *const u8,
+    secret_size: size_t,
+    account: *const c_char,
+    issuer: *const c_char,
+    counter: u64,
+    digits: c_int,
+    algorithm: DU_OTP_ALGORITHM,
+    output: *mut *mut c_char,
+) -> c_int {
+    if secret.is_null() || account.is_null() || !(6..=8).contains(&digits) || output.is_null() {
+        return -1;
+    }
+    let issuer = if issuer.is_null() {
+        None
+    } else {
+        Some(from_c_str!(issuer).unwrap())
+    };
+    let uri = otp::uri(
+        "hotp",
+        std::slice::from_raw_parts(secret, secret_size),
+        from_c_str!(account).unwrap(),
+        issuer,
+        digits as u32,
+        &algorithm,
+        ("counter", counter),
+    );
+    *output = to_c_str!(uri).unwrap().into_raw();
+    0
+}
+
+/// Reads the metadata of an A1 digital certificate stored in a PKCS#12 (`.pfx`/`.p12`) file.
+///
+/// # Arguments
+///
+/// * `[in] filename` - PKCS#12 filename as C-like string.
+/// * `[in] password` - Password of the PKCS#12 file as C-like string.
+/// * `[in,out] subject` - Certificate subject as C-like string.
+/// * `[in,out] issuer` - Certificate issuer as C-like string.
+/// * `[in,out] serial` - Certificate serial number in hexadecimal as C-like string.
+/// * `[in,out] not_before` - Start of the validity period as Unix time in seconds.
+/// * `[in,out] not_after` - End of the validity period as Unix time in seconds.
+/// * `[in,out] document` - CNPJ or CPF embedded in the subject as C-like string (empty if it does not exist).
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - File not found.
+/// * `-3` - Wrong password.
+/// * `-4` - Corrupted or invalid PKCS#12 file.
+/// * `-5` - Unknown error.
+#[no_mangle]
+pub unsafe extern "C" fn du_certificate_info(
+    filename: *const c_char,
+    password: *const c_char,
+    subject: *mut *mut c_char,
+    issuer: *mut *mut c_char,
+    serial: *mut *mut c_char,
+    not_before: *mut i64,
+    not_after: *mut i64,
+    document: *mut *mut c_char,
+) -> c_int {
+    if filename.is_null()
+        || password.is_null()
+        || subject.is_null()
+        || issuer.is_null()
+        || serial.is_null()
+        || not_before.is_null()
+        || not_after.is_null()
+        || document.is_null()
+    {
+        return -1;
+    }
+    let pkcs12 = match cert::load_pkcs12(
+        from_c_str!(filename).unwrap(),
+        from_c_str!(password).unwrap(),
+    ) {
+        Ok(pkcs12) => pkcs12,
+        Err(error) => return error,
+    };
+    let x509 = match pkcs12.cert {
+        Some(x509) => x509,
+        None => return -4,
+    };
+    let serial_hex = match x509.serial_number().to_bn().and_then(|bn| bn.to_hex_str()) {
+        Ok(hex) => hex.to_string(),
+        Err(_) => return -5,
+    };
+    match (
+        cert::to_unix_time(x509.not_before()),
+        cert::to_unix_time(x509.not_after()),
+    ) {
+        (Some(before), Some(after)) => {
+            *not_before = before;
+            *not_after = after;
+        }
+        _ => return -5,
+    }
+    *subject = to_c_str!(cert::name_to_string(x509.subject_name()))
+        .unwrap()
+        .into_raw();
+    *issuer = to_c_str!(cert::name_to_string(x509.issuer_name()))
+        .unwrap()
+        .into_raw();
+    *serial = to_c_str!(serial_hex).unwrap().into_raw();
+    *document = to_c_str!(cert::document(x509.subject_name()).unwrap_or_default())
+        .unwrap()
+        .into_raw();
+    0
+}
+
+/// Signs a XML document (e.g. NF-e/NFC-e) with an enveloped XMLDSig signature using C14N
+/// canonicalization, SHA-1 digest and RSA-SHA1 signature from an A1 certificate.
+///
+/// # Arguments
+///
+/// * `[in] xml` - XML document as C-like string.
+/// * `[in] tag` - Name of the element to be signed referenced by its `Id` attribute, e.g. `infNFe`,
+///   or null to sign the whole document.
+/// * `[in] filename` - PKCS#12 filename as C-like string.
+/// * `[in] password` - Password of the PKCS#12 file as C-like string.
+/// * `[in,out] output` - Signed XML document as C-like string.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - File not found.
+/// * `-3` - Wrong password.
+/// * `-4` - Corrupted or invalid PKCS#12 file.
+/// * `-5` - Unknown error.
+/// * `-6` - Invalid XML.
+/// * `-7` - Element not found.
+#[no_mangle]
+pub unsafe extern "C" fn du_xml_sign(
+    xml: *const c_char,
+    tag: *const c_char,
+    filename: *const c_char,
+    password: *const c_char,
+    output: *mut *mut c_char,
+) -> c_int {
+    if xml.is_null() || filename.is_null() || password.is_null() || output.is_null() {
+        return -1;
+    }
+    let pkcs12 = match cert::load_pkcs12(
+        from_c_str!(filename).unwrap(),
+        from_c_str!(password).unwrap(),
+    ) {
+        Ok(pkcs12) => pkcs12,
+        Err(error) => return error,
+    };
+    let xml = match from_c_str!(xml) {
+        Ok(xml) => xml,
+        Err(_) => return -6,
+    };
+    let tag = if tag.is_null() {
+        None
+    } else {
+        Some(from_c_str!(tag).unwrap())
+    };
+    match xmldsig::sign(xml, tag, &pkcs12) {
+        Ok(signed) => {
+            *output = to_c_str!(signed).unwrap().into_raw();
+            0
+        }
+        Err(error) => error,
+    }
+}
+
+/// Verifies an enveloped XMLDSig signature of a XML document against the certificate embedded in it.
+///
+/// # Arguments
+///
+/// * `[in] xml` - Signed XML document as C-like string.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Invalid XML.
+/// * `-3` - Signature not found.
+/// * `-4` - Digest mismatch, i.e. the signed content was changed.
+/// * `-5` - Invalid signature.
+/// * `-6` - Unsupported algorithm.
+#[no_mangle]
+pub unsafe extern "C" fn du_xml_verify(xml: *const c_char) -> c_int {
+    if xml.is_null() {
+        return -1;
+    }
+    let xml = match from_c_str!(xml) {
+        Ok(xml) => xml,
+        Err(_) => return -2,
+    };
+    match xmldsig::verify(xml) {
+        Ok(_) => 0,
+        Err(error) => error,
+    }
+}
+
+/// Options controlling the environment inherited by a child process.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct DU_ENV_OPTIONS {
+    /// Starts from an empty environment instead of inheriting the parent's one.
+    pub clear: bool,
+    /// Names of the variables kept when `clear` is set, as array of C-like string (optional).
+    pub inherit: *const *const c_char,
+    /// Names of the variables removed from the environment as array of C-like string (optional).
+    pub remove: *const *const c_char,
+}
+
+/// How a child process terminated.
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub enum DU_EXIT_KIND {
+    /// Exited normally, `code` holds the exit code.
+    #[allow(non_camel_case_types)]
+    DU_EXIT_CODE,
+    /// Killed by a signal, `code` holds the signal number.
+    #[allow(non_camel_case_types)]
+    DU_EXIT_SIGNAL,
+    /// Killed by a signal which produced a core dump, `code` holds the signal number.
+    #[allow(non_camel_case_types)]
+    DU_EXIT_CORE_DUMP,
+}
+
+/// Exit status of a child process.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct DU_EXIT_STATUS {
+    pub kind: DU_EXIT_KIND,
+    pub code: c_int,
+}
+
+/// Destinations for the output streams of a child process.
+#[repr(C)]
+pub enum DU_REDIRECT_MODE {
+    /// Inherits the stream of the parent process.
+    #[allow(non_camel_case_types)]
+    DU_REDIRECT_INHERIT,
+    /// Discards the stream.
+    #[allow(non_camel_case_types)]
+    DU_REDIRECT_NULL,
+    /// Writes the stream to the file at `path`, truncating it.
+    #[allow(non_camel_case_types)]
+    DU_REDIRECT_TRUNCATE,
+    /// Writes the stream to the end of the file at `path`.
+    #[allow(non_camel_case_types)]
+    DU_REDIRECT_APPEND,
+    /// Merges `stderr` into `stdout` (valid for `stderr` only).
+    #[allow(non_camel_case_types)]
+    DU_REDIRECT_STDOUT,
+}
+
+/// Redirection of an output stream of a child process.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct DU_REDIRECT {
+    pub mode: DU_REDIRECT_MODE,
+    pub path: *const c_char,
+}
+
+/// User and groups which a child process runs as, given by name or numeric ID (Linux only).
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct DU_CREDENTIALS {
+    /// User as C-like string, or null to keep the current one.
+    pub user: *const c_char,
+    /// Group as C-like string, or null to use the primary group of `user`.
+    pub group: *const c_char,
+    /// Supplementary groups as array of C-like string, or null to use the ones `user` is a member of.
+    pub groups: *const *const c_char,
+}
+
+/// Resource limits, nice value and umask of a child process (Linux only). Each limit is set as
+/// both the soft and the hard limit, so the program cannot raise it back.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct DU_LIMITS {
+    /// Maximum size in bytes of the virtual memory, or `0` to keep the current limit.
+    pub address_space: u64,
+    /// Maximum CPU time in seconds, or `0` to keep the current limit.
+    pub cpu_time: u64,
+    /// Maximum number of open files, or `0` to keep the current limit.
+    pub open_files: u64,
+    /// Nice value, from `-20` (highest priority) to `19` (lowest priority), or any value out of this
+    /// range, e.g. `100`, to keep the current one.
+    pub nice: c_int,
+    /// File mode creation mask, e.g. `0o027`, or `-1` to keep the current one.
+    pub umask: c_int,
+}
+
+/// Options controlling how a child process is created. Functions given an option which is not
+/// supported on the platform fail with `-1`.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct DU_SPAWN_OPTIONS {
+    /// Working directory as C-like string, or null to keep the current one.
+    pub workdir: *const c_char,
+    /// Runs a console program without creating a console window (Windows only).
+    pub hidden: bool,
+    /// Starts the program in a new process group.
+    pub new_process_group: bool,
+    /// Starts the program in a new console (Windows only).
+    pub new_console: bool,
+    /// Detaches the program from the calling process, so it keeps running after the calling
+    /// process exits: in a new session on Linux, or without a console in a new process group on
+    /// Windows. Cannot be combined with `new_console` or `kill_with_parent`.
+    pub detached: bool,
+    /// Lets the program inherit the file descriptors (besides the standard ones) of the calling
+    /// process. Required on Windows, where handles are always inherited.
+    pub inherit_handles: bool,
+    /// Kills all the descendants of the program along with it when it is killed on timeout,
+    /// overflow or by `du_process_kill`. On Linux, the program is started in its own process group.
+    pub kill_tree: bool,
+    /// Kills the program when the calling process dies, through the parent death signal on Linux
+    /// or a job object on Windows. On Linux, the program is spawned from a dedicated thread which
+    /// lives as long as the process, so it survives the calling thread.
+    pub kill_with_parent: bool,
+    /// Redirection of the `stdout` of the program, or null to inherit it. Must be null for the
+    /// functions which capture the output.
+    pub stdout: *const DU_REDIRECT,
+    /// Redirection of the `stderr` of the program, or null to inherit it. Must be null for the
+    /// functions which capture the output.
+    pub stderr: *const DU_REDIRECT,
+    /// User and groups to run the program as, or null to keep the current ones (Linux only).
+    pub credentials: *const DU_CREDENTIALS,
+    /// Resource limits, nice value and umask of the program, or null to keep the current ones
+    /// (Linux only).
+    pub limits: *const DU_LIMITS,
+}
+
+/// Executes the command as a child process.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] workdir` - Working directory as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] waiting` - Waiting for the program to exit completely.
+/// * `[in,out] exitcode` - Exit code of the process, or `128` plus the signal number if it was
+///   killed by a signal.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+#[no_mangle]
+pub unsafe extern "C" fn du_spawn(
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    waiting: bool,
+    exitcode: *mut c_int,
+) -> c_int {
+    if program.is_null() || (waiting && exitcode.is_null()) {
+        return -1;
+    }
+    let mut status = DU_EXIT_STATUS {
+        kind: DU_EXIT_KIND::DU_EXIT_CODE,
+        code: 0,
+    };
+    match spawn(
+        process::command(program, workdir, args, envs, ptr::null()),
+        None,
+        waiting,
+        0,
+        None,
+        ptr::null_mut(),
+        &mut status,
+    ) {
+        0 => {
+            if waiting {
+                *exitcode = exit_code(&status);
+            }
+            0
+        }
+        -1 => -1,
+        -2 => -2,
+        _ => -3,
+    }
+}
+
+/// Flattens an exit status into a single code, as shells do: the exit code, or `128` plus the
+/// signal number if the process was killed by a signal.
+fn exit_code(status: &DU_EXIT_STATUS) -> c_int {
+    match status.kind {
+        DU_EXIT_KIND::DU_EXIT_CODE => status.code,
+        _ => 128 + status.code,
+    }
+}
+
+/// Callback notified when a child process started without waiting exits.
+///
+/// # Arguments
+///
+/// * `[in] cls` - User-defined closure passed to `du_spawn_ex`.
+/// * `[in] pid` - Process identifier.
+/// * `[in] status` - Exit status of the process.
+#[allow(non_camel_case_types)]
+pub type DU_EXIT_CALLBACK = extern "C" fn(cls: *mut c_void, pid: c_uint, status: DU_EXIT_STATUS);
+
+/// Executes the command as a child process, created according to the given options.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in] options` - Options to create the process, or null to create it as `du_spawn()` does.
+/// * `[in] waiting` - Waiting for the program to exit completely.
+/// * `[in] timeout` - Time in milliseconds to wait before killing the program, or `0` to wait indefinitely.
+/// * `[in] callback` - Function called from a background thread when the program exits, if not
+///   waiting for it (optional).
+/// * `[in] cls` - User-defined closure passed to the `callback`.
+/// * `[in,out] status` - Exit status of the process.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out.
+/// * `-5` - Unable to open the redirection file.
+/// * `-6` - User or group not found.
+/// * `-7` - Not permitted to run the program as the given user or groups.
+#[no_mangle]
+pub unsafe extern "C" fn du_spawn_ex(
+    program: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    options: *const DU_SPAWN_OPTIONS,
+    waiting: bool,
+    timeout: c_uint,
+    callback: Option<DU_EXIT_CALLBACK>,
+    cls: *mut c_void,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if program.is_null() || (waiting && status.is_null()) {
+        return -1;
+    }
+    let options = options.as_ref();
+    let cmd = match spawn_command(program, args, envs, env_options, options) {
+        Ok(cmd) => cmd,
+        Err(code) => return code,
+    };
+    spawn(cmd, options, waiting, timeout, callback, cls, status)
+}
+
+/// Creates the command according to the spawn options.
+unsafe fn spawn_command(
+    program: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    options: Option<&DU_SPAWN_OPTIONS>,
+) -> Result<Command, c_int> {
+    let options = match options {
+        Some(options) => options,
+        None => {
+            return Ok(process::command(
+                program,
+                ptr::null(),
+                args,
+                envs,
+                env_options,
+            ))
+        }
+    };
+    let mut cmd = process::command(program, options.workdir, args, envs, env_options);
+    os::spawn_options(&mut cmd, options)?;
+    process::redirect(&mut cmd, options.stdout, options.stderr)?;
+    // The limits are applied while still privileged, before switching the user.
+    let limits = options.limits;
+    if !limits.is_null() {
+        let limits = &*limits;
+        os::limits(
+            &mut cmd,
+            limits.address_space,
+            limits.cpu_time,
+            limits.open_files,
+            limits.nice,
+            limits.umask,
+        )?;
+    }
+    let credentials = options.credentials;
+    if !credentials.is_null() {
+        let credentials = &*credentials;
+        let name = |name: *const c_char| {
+            if name.is_null() {
+                return Ok(None);
+            }
+            from_c_str!(name).map(Some).map_err(|_| -1)
+        };
+        let user = name(credentials.user)?;
+        let group = name(credentials.group)?;
+        let mut groups = None;
+        if !credentials.groups.is_null() {
+            let groups = groups.insert(Vec::new());
+            for i in 0.. {
+                match name(*credentials.groups.offset(i))? {
+                    Some(group) => groups.push(group),
+                    None => break,
+                }
+            }
+        }
+        os::credentials(&mut cmd, user, group, groups)?;
+    }
+    Ok(cmd)
+}
+
+/// Maps the error of spawning a command created by `spawn_command` into a return code.
+fn spawn_error(error: std::io::Error, options: Option<&DU_SPAWN_OPTIONS>) -> c_int {
+    if error.kind() == NotFound {
+        return -2;
+    }
+    // Only switching the user or groups fails with `EPERM`, running the program fails with
+    // `EACCES` instead.
+    if error.raw_os_error() == Some(libc::EPERM)
+        && options.is_some_and(|options| !options.credentials.is_null())
+    {
+        return -7;
+    }
+    -3
+}
+
+unsafe fn spawn(
+    mut cmd: Command,
+    options: Option<&DU_SPAWN_OPTIONS>,
+    waiting: bool,
+    timeout: c_uint,
+    callback: Option<DU_EXIT_CALLBACK>,
+    cls: *mut c_void,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if cfg!(test) && options.is_none_or(|options| options.stdout.is_null()) {
+        cmd.stdout(Stdio::null());
+    }
+    match os::spawn(&mut cmd, options) {
+        Ok(mut child) => {
+            if let Some(options) = options {
+                if let Err(code) = os::spawned(&mut child, options) {
+                    return code;
+                }
+            }
+            if waiting {
+                let tree = options.is_some_and(|options| options.kill_tree);
+                match process::wait(&mut child, process::deadline(timeout), tree) {
+                    Ok(Some(exit_status)) => *status = process::exit_status(exit_status),
+                    Ok(None) => return -4,
+                    Err(_) => return -3,
+                }
+            } else {
+                let cls = cls as usize;
+                // Reaps the process as soon as it exits, so it does not linger as a zombie.
+                std::thread::spawn(move || {
+                    if let Ok(exit_status) = child.wait() {
+                        if let Some(callback) = callback {
+                            let status = process::exit_status(exit_status);
+                            callback(cls as *mut c_void, child.id(), status);
+                        }
+                    }
+                });
+            }
+        }
+        Err(error) => return spawn_error(error, options),
+    }
+    0
+}
+
+/// Launches the command as a detached process, which keeps running after the calling process
+/// exits. It runs in a new session (or without a console on Windows), with the standard streams
+/// redirected to null and no other inherited file descriptors.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] workdir` - Working directory as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in,out] pid` - Process identifier.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+#[no_mangle]
+pub unsafe extern "C" fn du_spawn_detached(
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    pid: *mut c_uint,
+) -> c_int {
+    if program.is_null() {
         return -1;
     }
-    os::terminate(process_name)
-}
+    let options = DU_SPAWN_OPTIONS {
+        workdir,
+        hidden: false,
+        new_process_group: false,
+        new_console: false,
+        detached: true,
+        inherit_handles: cfg!(target_os = "windows"),
+        kill_tree: false,
+        kill_with_parent: false,
+        stdout: ptr::null(),
+        stderr: ptr::null(),
+        credentials: ptr::null(),
+        limits: ptr::null(),
+    };
+    let mut cmd = match spawn_command(program, args, envs, env_options, Some(&options)) {
+        Ok(cmd) => cmd,
+        Err(code) => return code,
+    };
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    match os::spawn(&mut cmd, Some(&options)) {
+        Ok(mut child) => {
+            if !pid.is_null() {
+                *pid = child.id();
+            }
+            // Reaps the process if it exits while the caller is still running.
+            std::thread::spawn(move || child.wait());
+        }
+        Err(error) => return spawn_error(error, Some(&options)),
+    }
+    0
+}
+
+/// Helpers used to run a program with administrative privileges.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum DU_ELEVATION {
+    /// `pkexec`, which asks for authorization through the polkit agent of the desktop session.
+    #[allow(non_camel_case_types)]
+    DU_ELEVATION_PKEXEC,
+    /// `sudo -n`, which only runs the program if no password is required.
+    #[allow(non_camel_case_types)]
+    DU_ELEVATION_SUDO,
+    /// Custom helper invoked as `helper program [args]`, which must exit with the same codes as
+    /// `pkexec` when the user cancels (`126`) or is not authorized (`127`).
+    #[allow(non_camel_case_types)]
+    DU_ELEVATION_CUSTOM,
+}
+
+/// Executes the command with administrative privileges through an elevation helper, waiting for
+/// it to finish (Linux only).
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] workdir` - Working directory as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the helper as array of C-like string, which
+///   usually does not pass them on to the program.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in] helper` - Elevation helper.
+/// * `[in] helper_path` - Path of the custom helper as C-like string, required by `DU_ELEVATION_CUSTOM`.
+/// * `[in] timeout` - Time in milliseconds to wait before killing the helper, or `0` to wait indefinitely.
+/// * `[in,out] status` - Exit status of the program, or of the helper when it failed.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument, or not supported on the platform.
+/// * `-2` - Helper not found.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out.
+/// * `-5` - Cancelled by the user, i.e. `pkexec` or the custom helper exited with `126`.
+/// * `-6` - Not authorized, i.e. `sudo` refused to run the program, or `pkexec` or the custom
+///   helper exited with `127`.
+///
+/// Since the program itself may exit with `126` or `127`, which `pkexec` passes on as is, `status`
+/// is also filled when `-5` or `-6` is returned, holding the raw exit status.
+#[no_mangle]
+pub unsafe extern "C" fn du_spawn_elevated(
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    helper: DU_ELEVATION,
+    helper_path: *const c_char,
+    timeout: c_uint,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if cfg!(not(target_os = "linux")) || program.is_null() || status.is_null() {
+        return -1;
+    }
+    let (path, options): (&str, &[&str]) = match helper {
+        DU_ELEVATION::DU_ELEVATION_PKEXEC => ("pkexec", &[]),
+        DU_ELEVATION::DU_ELEVATION_SUDO => ("sudo", &["-n", "--"]),
+        DU_ELEVATION::DU_ELEVATION_CUSTOM => {
+            match (!helper_path.is_null()).then(|| from_c_str!(helper_path)) {
+                Some(Ok(path)) => (path, &[]),
+                _ => return -1,
+            }
+        }
+    };
+    let mut cmd = process::elevated(path, options, program, workdir, args, envs, env_options);
+    if cfg!(test) {
+        cmd.stdout(Stdio::null());
+    }
+    let exit_status = match cmd.spawn() {
+        Ok(mut child) => match process::wait(&mut child, process::deadline(timeout), false) {
+            Ok(Some(exit_status)) => exit_status,
+            Ok(None) => return -4,
+            Err(_) => return -3,
+        },
+        Err(error) => {
+            if error.kind() == NotFound {
+                return -2;
+            }
+            return -3;
+        }
+    };
+    *status = process::exit_status(exit_status);
+    match helper {
+        DU_ELEVATION::DU_ELEVATION_SUDO => {
+            // sudo exits with `1` both when it refuses to run the program and when the program
+            // fails, so only then it is asked whether the program is allowed to run without a
+            // password, which does not run it again.
+            if exit_status.code() == Some(1) {
+                let mut check = process::elevated(
+                    "sudo",
+                    &["-n", "-l", "--"],
+                    program,
+                    workdir,
+                    args,
+                    envs,
+                    env_options,
+                );
+                check
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null());
+                match check.status() {
+                    Ok(exit_status) if exit_status.success() => {}
+                    Ok(_) => return -6,
+                    Err(_) => return -3,
+                }
+            }
+        }
+        _ => match exit_status.code() {
+            Some(126) => return -5,
+            Some(127) => return -6,
+            _ => {}
+        },
+    }
+    0
+}
+
+/// Actions taken when the output of a child process exceeds the maximum size.
+#[repr(C)]
+pub enum DU_OVERFLOW {
+    /// Discards the exceeding output, letting the process run.
+    #[allow(non_camel_case_types)]
+    DU_OVERFLOW_DISCARD,
+    /// Kills the process.
+    #[allow(non_camel_case_types)]
+    DU_OVERFLOW_KILL,
+}
+
+/// Executes the command as a child process waiting for it to finish and collecting all of its output.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] workdir` - Working directory as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in,out] output` - C-like string containing the `stdout` content if it exists.
+/// * `[in,out] error` - C-like string containing the `stderr` content if it exists.
+/// * `[in,out] exitcode` - Exit code of the process, or `128` plus the signal number if it was
+///   killed by a signal.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+#[no_mangle]
+pub unsafe extern "C" fn du_execute(
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    output: *mut *mut c_char,
+    error: *mut *mut c_char,
+    exitcode: *mut c_int,
+) -> c_int {
+    if program.is_null() || output.is_null() || error.is_null() || exitcode.is_null() {
+        return -1;
+    }
+    let mut cmd = process::command(program, workdir, args, envs, ptr::null());
+    match cmd.output() {
+        Ok(child) => {
+            *output = to_c_str!(String::from_utf8_lossy(&child.stdout).replace('\0', ""))
+                .unwrap()
+                .into_raw();
+            *error = to_c_str!(String::from_utf8_lossy(&child.stderr).replace('\0', ""))
+                .unwrap()
+                .into_raw();
+            *exitcode = exit_code(&process::exit_status(child.status));
+        }
+        Err(error) => {
+            if error.kind() == NotFound {
+                return -2;
+            }
+            return -3;
+        }
+    }
+    0
+}
+
+/// Executes the command as a child process, created according to the given options, waiting for
+/// it to finish and collecting all of its output.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in] options` - Options to create the process, or null to create it as `du_execute()` does.
+/// * `[in] input` - Data to write to the program `stdin` (optional).
+/// * `[in] input_size` - Size of the `input` data.
+/// * `[in] timeout` - Time in milliseconds to wait before killing the program, or `0` to wait indefinitely.
+/// * `[in] max_size` - Maximum size in bytes captured from each stream, or `0` for no limit.
+/// * `[in] overflow` - What to do when a stream exceeds `max_size`.
+/// * `[in] encoding` - Encoding of the program output to be decoded into UTF-8, i.e. `CP850`, `CP1252`,
+///   `ISO-8859-1` or `UTF-8`, or null to keep the output as is.
+/// * `[in,out] output` - Bytes containing the `stdout` content if it exists.
+/// * `[in,out] output_size` - Size of the `output` bytes.
+/// * `[in,out] error` - Bytes containing the `stderr` content if it exists.
+/// * `[in,out] error_size` - Size of the `error` bytes.
+/// * `[in,out] truncated` - Whether some output was discarded for exceeding `max_size`.
+/// * `[in,out] status` - Exit status of the process.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out, `output` and `error` contain what was captured until the program was killed.
+/// * `-5` - Output limit exceeded, `output` and `error` contain what was captured until the program
+///   was killed.
+/// * `-6` - User or group not found.
+/// * `-7` - Not permitted to run the program as the given user or groups.
+#[no_mangle]
+pub unsafe extern "C" fn du_execute_ex(
+    program: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    options: *const DU_SPAWN_OPTIONS,
+    input: *const u8,
+    input_size: size_t,
+    timeout: c_uint,
+    max_size: size_t,
+    overflow: DU_OVERFLOW,
+    encoding: *const c_char,
+    output: *mut *mut u8,
+    output_size: *mut size_t,
+    error: *mut *mut u8,
+    error_size: *mut size_t,
+    truncated: *mut bool,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if program.is_null()
+        || output.is_null()
+        || output_size.is_null()
+        || error.is_null()
+        || error_size.is_null()
+        || status.is_null()
+    {
+        return -1;
+    }
+    let options = options.as_ref();
+    if options.is_some_and(|options| !options.stdout.is_null() || !options.stderr.is_null()) {
+        return -1;
+    }
+    let cmd = match spawn_command(program, args, envs, env_options, options) {
+        Ok(cmd) => cmd,
+        Err(code) => return code,
+    };
+    execute(
+        cmd,
+        options,
+        input,
+        input_size,
+        timeout,
+        max_size,
+        overflow,
+        encoding,
+        output,
+        output_size,
+        error,
+        error_size,
+        truncated,
+        status,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn execute(
+    mut cmd: Command,
+    options: Option<&DU_SPAWN_OPTIONS>,
+    input: *const u8,
+    input_size: size_t,
+    timeout: c_uint,
+    max_size: size_t,
+    overflow: DU_OVERFLOW,
+    encoding: *const c_char,
+    output: *mut *mut u8,
+    output_size: *mut size_t,
+    error: *mut *mut u8,
+    error_size: *mut size_t,
+    truncated: *mut bool,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    let charset = if encoding.is_null() {
+        None
+    } else {
+        match from_c_str!(encoding).ok().and_then(Charset::from_name) {
+            Some(charset) => Some(charset),
+            None => return -1,
+        }
+    };
+    let input = if input.is_null() {
+        cmd.stdin(Stdio::null());
+        None
+    } else {
+        cmd.stdin(Stdio::piped());
+        Some(std::slice::from_raw_parts(input, input_size).to_vec())
+    };
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = match os::spawn(&mut cmd, options) {
+        Ok(child) => child,
+        Err(error) => return spawn_error(error, options),
+    };
+    if let Some(options) = options {
+        if let Err(code) = os::spawned(&mut child, options) {
+            return code;
+        }
+    }
+    let limit = if max_size == 0 { usize::MAX } else { max_size };
+    let kill = matches!(overflow, DU_OVERFLOW::DU_OVERFLOW_KILL);
+    let tree = options.is_some_and(|options| options.kill_tree);
+    match process::output(child, input, process::deadline(timeout), limit, kill, tree) {
+        Ok(captured) => {
+            match charset {
+                Some(charset) => {
+                    into_raw_bytes!(charset.decode(captured.stdout), output, output_size);
+                    into_raw_bytes!(charset.decode(captured.stderr), error, error_size);
+                }
+                None => {
+                    into_raw_bytes!(captured.stdout, output, output_size);
+                    into_raw_bytes!(captured.stderr, error, error_size);
+                }
+            }
+            if !truncated.is_null() {
+                *truncated = captured.truncated;
+            }
+            if kill && captured.truncated {
+                return -5;
+            }
+            match captured.status {
+                Some(exit_status) => *status = process::exit_status(exit_status),
+                None => return -4,
+            }
+        }
+        Err(_) => return -3,
+    }
+    0
+}
+
+/// Executes the command line through the system shell (`/bin/sh -c`, or `cmd /C` on Windows),
+/// waiting for it to finish and collecting all of its output.
+///
+/// # Arguments
+///
+/// * `[in] command` - Command line as C-like string, which may contain pipes and redirections.
+/// * `[in] workdir` - Working directory as C-like string.
+/// * `[in] envs` - Environment variables to pass to the shell as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in] input` - Data to write to the shell `stdin` (optional).
+/// * `[in] input_size` - Size of the `input` data.
+/// * `[in] timeout` - Time in milliseconds to wait before killing the shell, or `0` to wait indefinitely.
+/// * `[in] max_size` - Maximum size in bytes captured from each stream, or `0` for no limit.
+/// * `[in] overflow` - What to do when a stream exceeds `max_size`.
+/// * `[in] encoding` - Encoding of the output to be decoded into UTF-8, i.e. `CP850`, `CP1252`,
+///   `ISO-8859-1` or `UTF-8`, or null to keep the output as is.
+/// * `[in,out] output` - Bytes containing the `stdout` content if it exists.
+/// * `[in,out] output_size` - Size of the `output` bytes.
+/// * `[in,out] error` - Bytes containing the `stderr` content if it exists.
+/// * `[in,out] error_size` - Size of the `error` bytes.
+/// * `[in,out] truncated` - Whether some output was discarded for exceeding `max_size`.
+/// * `[in,out] status` - Exit status of the shell.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Shell not found.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out, `output` and `error` contain what was captured until the shell was killed.
+/// * `-5` - Output limit exceeded, `output` and `error` contain what was captured until the shell
+///   was killed.
+#[no_mangle]
+pub unsafe extern "C" fn du_shell_execute(
+    command: *const c_char,
+    workdir: *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    input: *const u8,
+    input_size: size_t,
+    timeout: c_uint,
+    max_size: size_t,
+    overflow: DU_OVERFLOW,
+    encoding: *const c_char,
+    output: *mut *mut u8,
+    output_size: *mut size_t,
+    error: *mut *mut u8,
+    error_size: *mut size_t,
+    truncated: *mut bool,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if command.is_null()
+        || output.is_null()
+        || output_size.is_null()
+        || error.is_null()
+        || error_size.is_null()
+        || status.is_null()
+    {
+        return -1;
+    }
+    execute(
+        process::shell(command, workdir, envs, env_options),
+        None,
+        input,
+        input_size,
+        timeout,
+        max_size,
+        overflow,
+        encoding,
+        output,
+        output_size,
+        error,
+        error_size,
+        truncated,
+        status,
+    )
+}
+
+/// Quotes the arguments to be safely passed to the system shell by `du_shell_execute`.
+///
+/// # Arguments
+///
+/// * `[in] args` - Arguments to quote as array of C-like string.
+/// * `[in,out] output` - Quoted arguments joined by spaces as C-like string.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+#[no_mangle]
+pub unsafe extern "C" fn du_shell_quote(
+    args: *const *const c_char,
+    output: *mut *mut c_char,
+) -> c_int {
+    if args.is_null() || output.is_null() {
+        return -1;
+    }
+    *output = to_c_str!(shell::quote(&from_c_array!(args)))
+        .unwrap()
+        .into_raw();
+    0
+}
+
+/// Output streams of a child process.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DU_STREAM {
+    #[allow(non_camel_case_types)]
+    DU_STREAM_STDOUT,
+    #[allow(non_camel_case_types)]
+    DU_STREAM_STDERR,
+}
+
+/// Callback receiving a chunk of the output of a child process.
+///
+/// # Arguments
+///
+/// * `[in] cls` - User-defined closure passed to `du_execute_stream`.
+/// * `[in] stream` - Stream which the chunk was read from.
+/// * `[in] data` - Bytes of the chunk.
+/// * `[in] size` - Size of the `data`.
+///
+/// # Returns
+///
+/// * `true` - Continue the execution.
+/// * `false` - Cancel the execution, killing the process.
+#[allow(non_camel_case_types)]
+pub type DU_STREAM_CALLBACK =
+    extern "C" fn(cls: *mut c_void, stream: DU_STREAM, data: *const u8, size: size_t) -> bool;
+
+/// Executes the command as a child process, passing its output to a callback as it arrives.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in] options` - Options to create the process, without redirections (optional).
+/// * `[in] input` - Bytes to write to the `stdin` of the program (optional).
+/// * `[in] input_size` - Size of the `input`.
+/// * `[in] timeout` - Time in milliseconds to wait before killing the program, or `0` to wait indefinitely.
+/// * `[in] callback` - Function called with each chunk read from `stdout` and `stderr`.
+/// * `[in] cls` - User-defined closure passed to the `callback`.
+/// * `[in,out] status` - Exit status of the process.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out.
+/// * `-5` - Cancelled by the callback.
+/// * `-6` - User or group not found.
+/// * `-7` - Not permitted to run the program as the given user or groups.
+#[no_mangle]
+pub unsafe extern "C" fn du_execute_stream(
+    program: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    options: *const DU_SPAWN_OPTIONS,
+    input: *const u8,
+    input_size: size_t,
+    timeout: c_uint,
+    callback: Option<DU_STREAM_CALLBACK>,
+    cls: *mut c_void,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    let callback = match callback {
+        Some(callback) if !program.is_null() && !status.is_null() => callback,
+        _ => return -1,
+    };
+    let options = options.as_ref();
+    if options.is_some_and(|options| !options.stdout.is_null() || !options.stderr.is_null()) {
+        return -1;
+    }
+    let mut cmd = match spawn_command(program, args, envs, env_options, options) {
+        Ok(cmd) => cmd,
+        Err(code) => return code,
+    };
+    let input = if input.is_null() {
+        cmd.stdin(Stdio::null());
+        None
+    } else {
+        cmd.stdin(Stdio::piped());
+        Some(std::slice::from_raw_parts(input, input_size).to_vec())
+    };
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = match os::spawn(&mut cmd, options) {
+        Ok(child) => child,
+        Err(error) => return spawn_error(error, options),
+    };
+    if let Some(options) = options {
+        if let Err(code) = os::spawned(&mut child, options) {
+            return code;
+        }
+    }
+    let tree = options.is_some_and(|options| options.kill_tree);
+    let deadline = process::deadline(timeout);
+    let termination = process::stream(child, input, deadline, tree, |stream, data| {
+        callback(cls, stream, data.as_ptr(), data.len())
+    });
+    match termination {
+        Ok(process::Termination::Exited(exit_status)) => {
+            *status = process::exit_status(exit_status)
+        }
+        Ok(process::Termination::TimedOut) => return -4,
+        Ok(process::Termination::Cancelled) => return -5,
+        Err(_) => return -3,
+    }
+    0
+}
+
+/// Executes the command as a child process attached to a pseudo-terminal, waiting for it to finish
+/// and collecting all of its terminal output, for programs which behave differently when not run
+/// from a terminal.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] workdir` - Working directory as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in] input` - Data to type into the terminal, e.g. `"yes\n"` or `"\x04"` for end of file (optional).
+/// * `[in] input_size` - Size of the `input` data.
+/// * `[in] timeout` - Time in milliseconds to wait before killing the program, or `0` to wait indefinitely.
+/// * `[in] rows` - Number of rows of the terminal, or `0` for 24.
+/// * `[in] cols` - Number of columns of the terminal, or `0` for 80.
+/// * `[in,out] output` - Bytes containing the terminal output (including the echoed input).
+/// * `[in,out] output_size` - Size of the `output` bytes.
+/// * `[in,out] status` - Exit status of the process.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out, `output` contains what was captured until the program was killed.
+/// * `-5` - Not supported on this platform.
+#[no_mangle]
+pub unsafe extern "C" fn du_execute_pty(
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    input: *const u8,
+    input_size: size_t,
+    timeout: c_uint,
+    rows: c_ushort,
+    cols: c_ushort,
+    output: *mut *mut u8,
+    output_size: *mut size_t,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if program.is_null() || output.is_null() || output_size.is_null() || status.is_null() {
+        return -1;
+    }
+    let mut cmd = process::command(program, workdir, args, envs, env_options);
+    let rows = if rows == 0 { 24 } else { rows };
+    let cols = if cols == 0 { 80 } else { cols };
+    let master = match os::pty(&mut cmd, rows, cols) {
+        Ok(master) => master,
+        Err(error) => {
+            if error.kind() == Unsupported {
+                return -5;
+            }
+            return -3;
+        }
+    };
+    let child = cmd.spawn();
+    // Closes the terminal copies held by the command, so the end of the output can be detected.
+    drop(cmd);
+    let child = match child {
+        Ok(child) => child,
+        Err(error) => {
+            if error.kind() == NotFound {
+                return -2;
+            }
+            return -3;
+        }
+    };
+    let input = if input.is_null() {
+        None
+    } else {
+        Some(std::slice::from_raw_parts(input, input_size).to_vec())
+    };
+    match process::terminal(child, master, input, process::deadline(timeout)) {
+        Ok((data, exit_status)) => {
+            into_raw_bytes!(data, output, output_size);
+            match exit_status {
+                Some(exit_status) => *status = process::exit_status(exit_status),
+                None => return -4,
+            }
+        }
+        Err(_) => return -3,
+    }
+    0
+}
+
+/// Stage of a pipeline executed by `du_pipeline_execute`.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct DU_PIPELINE_STAGE {
+    /// Program path as C-like string.
+    pub program: *const c_char,
+    /// Arguments to pass to the program as array of C-like string (optional).
+    pub args: *const *const c_char,
+    /// Environment variables to pass to the program as array of C-like string (optional).
+    pub envs: *const *const c_char,
+    /// Options to clear or filter the inherited environment (optional).
+    pub env_options: *const DU_ENV_OPTIONS,
+    /// Options to create the process, without redirections (optional).
+    pub options: *const DU_SPAWN_OPTIONS,
+}
+
+/// Executes the stages as child processes connected by pipes, i.e. `stage1 | stage2 | stage3`,
+/// without a shell, waiting for all of them to finish.
+///
+/// # Arguments
+///
+/// * `[in] stages` - Array of stages of the pipeline.
+/// * `[in] count` - Number of `stages`.
+/// * `[in] input` - Data to write to the `stdin` of the first stage (optional).
+/// * `[in] input_size` - Size of the `input` data.
+/// * `[in] timeout` - Time in milliseconds to wait before killing the pipeline, or `0` to wait indefinitely.
+/// * `[in,out] output` - Bytes containing the `stdout` content of the last stage if it exists.
+/// * `[in,out] output_size` - Size of the `output` bytes.
+/// * `[in,out] error` - Bytes containing the `stderr` content of all stages if it exists.
+/// * `[in,out] error_size` - Size of the `error` bytes.
+/// * `[in,out] statuses` - Array receiving the exit status of each stage, with `count` items.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out, `output` and `error` contain what was captured until the pipeline was killed.
+/// * `-6` - User or group not found.
+/// * `-7` - Not permitted to run some stage as the given user or groups.
+#[no_mangle]
+pub unsafe extern "C" fn du_pipeline_execute(
+    stages: *const DU_PIPELINE_STAGE,
+    count: size_t,
+    input: *const u8,
+    input_size: size_t,
+    timeout: c_uint,
+    output: *mut *mut u8,
+    output_size: *mut size_t,
+    error: *mut *mut u8,
+    error_size: *mut size_t,
+    statuses: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if stages.is_null()
+        || count == 0
+        || output.is_null()
+        || output_size.is_null()
+        || error.is_null()
+        || error_size.is_null()
+        || statuses.is_null()
+    {
+        return -1;
+    }
+    let stages = std::slice::from_raw_parts(stages, count);
+    if stages.iter().any(|stage| stage.program.is_null()) {
+        return -1;
+    }
+    let mut commands = Vec::with_capacity(count);
+    for stage in stages {
+        let options = stage.options.as_ref();
+        if options.is_some_and(|options| !options.stdout.is_null() || !options.stderr.is_null()) {
+            return -1;
+        }
+        match spawn_command(
+            stage.program,
+            stage.args,
+            stage.envs,
+            stage.env_options,
+            options,
+        ) {
+            Ok(cmd) => commands.push((cmd, options)),
+            Err(code) => return code,
+        }
+    }
+    let mut children = match process::spawn_pipeline(commands, !input.is_null()) {
+        Ok(children) => children,
+        Err((index, error)) => return spawn_error(error, stages[index].options.as_ref()),
+    };
+    for i in 0..count {
+        if let Some(options) = stages[i].options.as_ref() {
+            if let Err(code) = os::spawned(&mut children[i].0, options) {
+                for (child, tree) in children.iter_mut() {
+                    process::kill(child, *tree).unwrap_or_default();
+                }
+                return code;
+            }
+        }
+    }
+    let input = if input.is_null() {
+        None
+    } else {
+        Some(std::slice::from_raw_parts(input, input_size).to_vec())
+    };
+    match process::pipeline(children, input, process::deadline(timeout)) {
+        Ok(captured) => {
+            into_raw_bytes!(captured.stdout, output, output_size);
+            into_raw_bytes!(captured.stderr, error, error_size);
+            match captured.statuses {
+                Some(exit_statuses) => {
+                    for (i, exit_status) in exit_statuses.into_iter().enumerate() {
+                        *statuses.add(i) = process::exit_status(exit_status);
+                    }
+                }
+                None => return -4,
+            }
+        }
+        Err(_) => return -3,
+    }
+    0
+}
+
+/// Opaque handle of a process started by `du_process_start`.
+#[allow(non_camel_case_types)]
+pub struct DU_PROCESS {
+    child: Child,
+    tree: bool,
+}
+
+/// Starts the command as a child process, keeping a handle to supervise it.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] workdir` - Working directory as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in,out] handle` - Handle of the process, which must be released by `du_process_free`.
+/// * `[in,out] pid` - Process identifier.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+#[no_mangle]
+pub unsafe extern "C" fn du_process_start(
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    handle: *mut *mut DU_PROCESS,
+    pid: *mut c_uint,
+) -> c_int {
+    if program.is_null() || handle.is_null() {
+        return -1;
+    }
+    process_start(
+        process::command(program, workdir, args, envs, env_options),
+        None,
+        handle,
+        pid,
+    )
+}
+
+/// Starts the command as a child process, created according to the given options, keeping a
+/// handle to supervise it.
+///
+/// # Arguments
+///
+/// * `[in] program` - Program path as C-like string.
+/// * `[in] args` - Arguments to pass to the program as array of C-like string.
+/// * `[in] envs` - Environment variables to pass to the program as array of C-like string.
+/// * `[in] env_options` - Options to clear or filter the inherited environment (optional).
+/// * `[in] options` - Options to create the process, or null to create it as `du_process_start()` does.
+/// * `[in,out] handle` - Handle of the process, which must be released by `du_process_free`.
+/// * `[in,out] pid` - Process identifier.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Program not found.
+/// * `-3` - Unknown error.
+/// * `-5` - Unable to open the redirection file.
+/// * `-6` - User or group not found.
+/// * `-7` - Not permitted to run the program as the given user or groups.
+#[no_mangle]
+pub unsafe extern "C" fn du_process_start_ex(
+    program: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+    options: *const DU_SPAWN_OPTIONS,
+    handle: *mut *mut DU_PROCESS,
+    pid: *mut c_uint,
+) -> c_int {
+    if program.is_null() || handle.is_null() {
+        return -1;
+    }
+    let options = options.as_ref();
+    let cmd = match spawn_command(program, args, envs, env_options, options) {
+        Ok(cmd) => cmd,
+        Err(code) => return code,
+    };
+    process_start(cmd, options, handle, pid)
+}
+
+unsafe fn process_start(
+    mut cmd: Command,
+    options: Option<&DU_SPAWN_OPTIONS>,
+    handle: *mut *mut DU_PROCESS,
+    pid: *mut c_uint,
+) -> c_int {
+    if cfg!(test) && options.is_none_or(|options| options.stdout.is_null()) {
+        cmd.stdout(Stdio::null());
+    }
+    match os::spawn(&mut cmd, options) {
+        Ok(mut child) => {
+            if let Some(options) = options {
+                if let Err(code) = os::spawned(&mut child, options) {
+                    return code;
+                }
+            }
+            if !pid.is_null() {
+                *pid = child.id();
+            }
+            let tree = options.is_some_and(|options| options.kill_tree);
+            *handle = Box::into_raw(Box::new(DU_PROCESS { child, tree }));
+        }
+        Err(error) => return spawn_error(error, options),
+    }
+    0
+}
+
+/// Waits for a process started by `du_process_start` to exit.
+///
+/// # Arguments
+///
+/// * `[in] handle` - Handle of the process.
+/// * `[in] timeout` - Time in milliseconds to wait for the process, or `0` to wait indefinitely.
+/// * `[in,out] status` - Exit status of the process.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-3` - Unknown error.
+/// * `-4` - Timed out, the process is still running.
+#[no_mangle]
+pub unsafe extern "C" fn du_process_wait(
+    handle: *mut DU_PROCESS,
+    timeout: c_uint,
+    status: *mut DU_EXIT_STATUS,
+) -> c_int {
+    if handle.is_null() || status.is_null() {
+        return -1;
+    }
+    match process::poll(&mut (*handle).child, process::deadline(timeout)) {
+        Ok(Some(exit_status)) => *status = process::exit_status(exit_status),
+        Ok(None) => return -4,
+        Err(_) => return -3,
+    }
+    0
+}
+
+/// Checks if a process started by `du_process_start` is still running.
+///
+/// # Arguments
+///
+/// * `[in] handle` - Handle of the process.
+///
+/// # Returns
+///
+/// * `true` - Running.
+/// * `false` - Exited.
+#[no_mangle]
+pub unsafe extern "C" fn du_process_is_running(handle: *mut DU_PROCESS) -> bool {
+    if handle.is_null() {
+        return false;
+    }
+    matches!((*handle).child.try_wait(), Ok(None))
+}
+
+/// Kills a process started by `du_process_start`, along with all of its descendants if it was
+/// started with the `kill_tree` option.
+///
+/// # Arguments
+///
+/// * `[in] handle` - Handle of the process.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-3` - Unknown error, e.g. not permitted to kill the process.
+#[no_mangle]
+pub unsafe extern "C" fn du_process_kill(handle: *mut DU_PROCESS) -> c_int {
+    if handle.is_null() {
+        return -1;
+    }
+    match process::kill(&mut (*handle).child, (*handle).tree) {
+        Ok(()) => 0,
+        Err(_) => -3,
+    }
+}
+
+/// Frees the handle of a process started by `du_process_start`. The process is not killed, but
+/// reaped in the background once it exits.
+///
+/// # Arguments
+///
+/// * `[in] handle` - Handle of the process.
+#[no_mangle]
+pub unsafe extern "C" fn du_process_free(handle: *mut DU_PROCESS) {
+    if handle.is_null() {
+        return;
+    }
+    let mut process = Box::from_raw(handle);
+    if let Ok(None) = process.child.try_wait() {
+        // Reaps the process as soon as it exits, so it does not linger as a zombie.
+        std::thread::spawn(move || process.child.wait());
+    }
+}
+
+/// Opens a file or link with the system default program.
+///
+/// # Arguments
+///
+/// * `[in] path` - File or link as C-like string.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Unknown error.
+#[no_mangle]
+pub unsafe extern "C" fn du_open(path: *const c_char) -> c_int {
+    if path.is_null() {
+        return -1;
+    }
+    match opener::open(from_c_str!(path).unwrap()) {
+        Ok(_) => 0,
+        Err(_) => -2,
+    }
+}
+
+/// Checks if there are any other running instance.
+///
+/// # Arguments
+///
+/// * `[in] identifier` - Unique path/name as C-like string to identify the instance.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Already running.
+/// * `-3` - Unknown error.
+#[no_mangle]
+pub unsafe extern "C" fn du_once(identifier: *const c_char) -> c_int {
+    if identifier.is_null() {
+        return -1;
+    }
+    match SingleInstance::new(from_c_str!(identifier).unwrap()) {
+        Ok(instance) => {
+            static mut RUNNING_INSTANCE: Option<SingleInstance> = None;
+            RUNNING_INSTANCE = Some(instance);
+            match RUNNING_INSTANCE {
+                Some(ref instance) => {
+                    if instance.is_single() {
+                        0
+                    } else {
+                        -2
+                    }
+                }
+                None => -3,
+            }
+        }
+        Err(_) => -3,
+    }
+}
+
+/// Calls the OS-specific function to shut down the machine.
+///
+/// # Arguments
+///
+/// * `[in] forced` - Forces the machine to shut down instantly without confirmations.
+/// * `[in,out] error_msg` - Error message as C-like string returned by the OS when the function fails.
+/// * `[in] error_size` - Size of the error message.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - OS error.
+#[no_mangle]
+pub unsafe extern "C" fn du_shutdown(
+    forced: bool,
+    error_msg: *mut c_char,
+    error_size: size_t,
+) -> c_int {
+    if error_msg.is_null() || error_size == 0 {
+        return -1;
+    }
+    let result = if forced { force_shutdown() } else { shutdown() };
+    match result {
+        Ok(_) => 0,
+        Err(error) => {
+            let msg = to_c_str!(error.to_string()).unwrap();
+            copy_c_str!(msg, error_msg, error_size);
+            -2
+        }
+    }
+}
+
+/// Calls the OS-specific function to reboot the machine.
+///
+/// # Arguments
+///
+/// * `[in] forced` - Forces the machine to reboot instantly without confirmations.
+/// * `[in,out] error_msg` - Error message as C-like string returned by the OS when the function fails.
+/// * `[in] error_size` - Size of the error message.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - OS error.
+#[no_mangle]
+pub unsafe extern "C" fn du_reboot(
+    forced: bool,
+    error_msg: *mut c_char,
+    error_size: size_t,
+) -> c_int {
+    if error_msg.is_null() || error_size == 0 {
+        return -1;
+    }
+    let result = if forced { force_reboot() } else { reboot() };
+    match result {
+        Ok(_) => 0,
+        Err(error) => {
+            let msg = to_c_str!(error.to_string()).unwrap();
+            copy_c_str!(msg, error_msg, error_size);
+            -2
+        }
+    }
+}
+
+/// Calls the OS-specific function to log out the user.
+///
+/// # Arguments
+///
+/// * `[in] forced` - Forces the machine to log out instantly without confirmations.
+/// * `[in,out] error_msg` - Error message as C-like string returned by the OS when the function fails.
+/// * `[in] error_size` - Size of the error message.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - OS error.
+#[no_mangle]
+pub unsafe extern "C" fn du_logout(
+    forced: bool,
+    error_msg: *mut c_char,
+    error_size: size_t,
+) -> c_int {
+    if error_msg.is_null() || error_size == 0 {
+        return -1;
+    }
+    let result = if forced { force_logout() } else { logout() };
+    match result {
+        Ok(_) => 0,
+        Err(error) => {
+            let msg = to_c_str!(error.to_string()).unwrap();
+            copy_c_str!(msg, error_msg, error_size);
+            -2
+        }
+    }
+}
+
+/// Available lock keys for handling, i.e. Capital Lock, Number Lock and Scrolling Lock.
+#[repr(C)]
+pub enum DU_LOCKKEY {
+    #[allow(non_camel_case_types)]
+    DU_LK_CAPSLOCK,
+    #[allow(non_camel_case_types)]
+    DU_LK_NUMLOCK,
+    #[allow(non_camel_case_types)]
+    DU_LK_SCROLLLOCK,
+}
+
+/// Enables the lock key.
+///
+/// # Arguments
+///
+/// * `[in] key` - The key to set a new state.
+/// * `[in] enabled` - The new state (enabled/disabled) for the key.
+#[no_mangle]
+pub unsafe extern "C" fn du_lockkey_set(key: DU_LOCKKEY, enabled: bool) {
+    let lockkey = LockKey::new();
+    lockkey.set(key.into(), enabled.into()).unwrap();
+}
+
+/// Retrieve the lock key state.
+///
+/// # Arguments
+///
+/// * `[in] key` - The key to set a new state.
+///
+/// # Returns
+///
+/// * `true` - Enabled.
+/// * `false` - Disabled.
+#[no_mangle]
+pub unsafe extern "C" fn du_lockkey_state(key: DU_LOCKKEY) -> bool {
+    let lockkey = LockKey::new();
+    lockkey.state(key.into()).unwrap().into()
+}
+
+/// Calls the OS-specific function to change the system date and time.
+///
+/// # Arguments
+///
+/// * `[in] year` - The year to be changed.
+/// * `[in] month` - The month to be changed.
+/// * `[in] day` - The day to be changed.
+/// * `[in] hour` - The hour to be changed.
+/// * `[in] minute` - The minute to be changed.
+/// * `[in] second` - The second to be changed.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Operation not permitted.
+/// * `-3` - OS error.
+#[no_mangle]
+pub unsafe extern "C" fn du_datetime_set(
+    year: c_int,
+    month: c_int,
+    day: c_int,
+    hour: c_int,
+    minute: c_int,
+    second: c_int,
+) -> c_int {
+    os::datetime_set(year, month, day, hour, minute, second)
+}
+
+/// Calls the OS-specific function to terminate a process.
+///
+/// # Arguments
+///
+/// * `[in] process_name` - The process name to be terminated.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - No process found.
+/// * `-3` - Operation not permitted.
+/// * `-4` - OS error.
+#[no_mangle]
+pub unsafe extern "C" fn du_terminate(process_name: *const c_char) -> c_int {
+    if process_name.is_null() {
+        return -1;
+    }
+    os::terminate(process_name)
+}
+
+/// Deletes one or more files matching their paths against Unix shell style patterns.
+///
+/// # Arguments
+///
+/// * `[in] pattern` - Pattern in Unix shell style.
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Not found.
+/// * `-3` - OS error.
+#[no_mangle]
+pub unsafe extern "C" fn du_deltree(pattern: *const c_char) -> c_int {
+    if pattern.is_null() {
+        return -1;
+    }
+    let mut found: c_int = -2;
+    for entry in glob(from_c_str!(pattern).unwrap()).unwrap() {
+        match entry {
+            Ok(path) => {
+                if path.is_dir() {
+                    fs::remove_dir_all(path).unwrap_or_default();
+                } else {
+                    fs::remove_file(path).unwrap_or_default();
+                }
+                found = 0;
+            }
+            Err(_) => return -3,
+        }
+    }
+    found
+}
+
+/// Securely deletes one or more files matching their paths against Unix shell style patterns,
+/// overwriting their contents before unlinking them.
+///
+/// # Arguments
+///
+/// * `[in] pattern` - Pattern in Unix shell style.
+/// * `[in] passes` - Number of overwrite passes, alternating the `0x00`, `0xFF`, `0x55` and `0xAA` bytes.
+/// * `[in,out] files` - Number of wiped files (optional).
+/// * `[in,out] bytes` - Number of wiped bytes (optional).
+///
+/// # Returns
+///
+/// * `0` - Success.
+/// * `-1` - Invalid argument.
+/// * `-2` - Not found.
+/// * `-3` - OS error.
+#[no_mangle]
+pub unsafe extern "C" fn du_deltree_secure(
+    pattern: *const c_char,
+    passes: c_uint,
+    files: *mut u64,
+    bytes: *mut u64,
+) -> c_int {
+    if pattern.is_null() || passes == 0 {
+        return -1;
+    }
+    let entries = match from_c_str!(pattern).map(glob) {
+        Ok(Ok(entries)) => entries,
+        _ => return -1,
+    };
+    let mut found: c_int = -2;
+    let mut wiped = (0, 0);
+    for entry in entries {
+        match entry {
+            Ok(path) => {
+                if wipe_tree(&path, passes, &mut wiped).is_err() {
+                    found = -3;
+                    break;
+                }
+                found = 0;
+            }
+            Err(_) => {
+                found = -3;
+                break;
+            }
+        }
+    }
+    if !files.is_null() {
+        *files = wiped.0;
+    }
+    if !bytes.is_null() {
+        *bytes = wiped.1;
+    }
+    found
+}
+
+fn wipe_tree(path: &Path, passes: c_uint, wiped: &mut (u64, u64)) -> std::io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            wipe_tree(&entry?.path(), passes, wiped)?;
+        }
+        fs::remove_dir(path)
+    } else {
+        if metadata.is_file() {
+            wiped.1 += wipe_file(path, passes)?;
+            wiped.0 += 1;
+        }
+        fs::remove_file(path)
+    }
+}
+
+fn wipe_file(path: &Path, passes: c_uint) -> std::io::Result<u64> {
+    const PATTERNS: [u8; 4] = [0x00, 0xFF, 0x55, 0xAA];
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let size = file.metadata()?.len();
+    for pass in 0..passes as usize {
+        let buf = [PATTERNS[pass % PATTERNS.len()]; BUFFER_SIZE];
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = size;
+        while remaining > 0 {
+            let n = remaining.min(BUFFER_SIZE as u64) as usize;
+            file.write_all(&buf[..n])?;
+            remaining -= n as u64;
+        }
+        file.sync_all()?;
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version() {
+        unsafe {
+            assert_eq!(
+                from_c_str!(du_version()).unwrap(),
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+    }
+
+    #[test]
+    fn md5() {
+        unsafe {
+            let hash: [c_char; 33] = [0; 33];
+            assert_eq!(du_md5(ptr::null(), hash.as_ptr() as *mut c_char, 33), -1);
+            assert_eq!(
+                du_md5(to_c_str!("abc123").unwrap().as_ptr(), ptr::null_mut(), 33),
+                -1
+            );
+            assert_eq!(
+                du_md5(
+                    to_c_str!("abc123").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    0
+                ),
+                -1
+            );
+            assert_eq!(
+                du_md5(
+                    to_c_str!("abc123").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    hash.len()
+                ),
+                0
+            );
+            assert_eq!(
+                from_c_str!(hash.as_ptr()).unwrap(),
+                "e99a18c428cb38d5f260853678922e03"
+            );
+        }
+    }
+
+    #[test]
+    fn md5_file() {
+        unsafe {
+            let hash: [c_char; 33] = [0; 33];
+            assert_eq!(
+                du_md5_file(ptr::null(), hash.as_ptr() as *mut c_char, 33),
+                -1
+            );
+            assert_eq!(
+                du_md5_file(
+                    to_c_str!("abc123.txt").unwrap().as_ptr(),
+                    ptr::null_mut(),
+                    33
+                ),
+                -1
+            );
+            assert_eq!(
+                du_md5_file(
+                    to_c_str!("abc123.txt").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    0
+                ),
+                -1
+            );
+            assert_eq!(
+                du_md5_file(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    33
+                ),
+                -2
+            );
+            assert_eq!(
+                du_md5_file(
+                    to_c_str!("LICENSE").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    hash.len()
+                ),
+                0
+            );
+            assert_eq!(
+                from_c_str!(hash.as_ptr()).unwrap(),
+                "d88e9e08385d2a17052dac348bde4bc1"
+            );
+        }
+    }
+
+    #[test]
+    fn sha1() {
+        unsafe {
+            let hash: [c_char; 41] = [0; 41];
+            assert_eq!(du_sha1(ptr::null(), hash.as_ptr() as *mut c_char, 41), -1);
+            assert_eq!(
+                du_sha1(to_c_str!("abc123").unwrap().as_ptr(), ptr::null_mut(), 41),
+                -1
+            );
+            assert_eq!(
+                du_sha1(
+                    to_c_str!("abc123").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    0
+                ),
+                -1
+            );
+            assert_eq!(
+                du_sha1(
+                    to_c_str!("abc123").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    hash.len()
+                ),
+                0
+            );
+            assert_eq!(
+                from_c_str!(hash.as_ptr()).unwrap(),
+                "6367c48dd193d56ea7b0baad25b19455e529f5ee"
+            );
+        }
+    }
+
+    #[test]
+    fn sha1_file() {
+        unsafe {
+            let hash: [c_char; 41] = [0; 41];
+            assert_eq!(
+                du_sha1_file(ptr::null(), hash.as_ptr() as *mut c_char, 41),
+                -1
+            );
+            assert_eq!(
+                du_sha1_file(
+                    to_c_str!("abc123.txt").unwrap().as_ptr(),
+                    ptr::null_mut(),
+                    41
+                ),
+                -1
+            );
+            assert_eq!(
+                du_sha1_file(
+                    to_c_str!("abc123.txt").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    0
+                ),
+                -1
+            );
+            assert_eq!(
+                du_sha1_file(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    41
+                ),
+                -2
+            );
+            assert_eq!(
+                du_sha1_file(
+                    to_c_str!("LICENSE").unwrap().as_ptr(),
+                    hash.as_ptr() as *mut c_char,
+                    hash.len()
+                ),
+                0
+            );
+            assert_eq!(
+                from_c_str!(hash.as_ptr()).unwrap(),
+                "6d842099530d126dea37db858a755e444f4de3f7"
+            );
+        }
+    }
+
+    #[test]
+    fn base64_encode() {
+        unsafe {
+            let mut output: *mut c_char = ptr::null_mut();
+            let data = b"foob\xfb\xff";
+            assert_eq!(
+                du_base64_encode(ptr::null(), 0, false, true, &mut output),
+                -1
+            );
+            assert_eq!(
+                du_base64_encode(data.as_ptr(), data.len(), false, true, ptr::null_mut()),
+                -1
+            );
+            assert_eq!(
+                du_base64_encode(data.as_ptr(), data.len(), false, true, &mut output),
+                0
+            );
+            assert_eq!(from_c_str!(output).unwrap(), "Zm9vYvv/");
+            du_dispose(output);
+            assert_eq!(
+                du_base64_encode(data.as_ptr(), data.len(), true, true, &mut output),
+                0
+            );
+            assert_eq!(from_c_str!(output).unwrap(), "Zm9vYvv_");
+            du_dispose(output);
+            assert_eq!(
+                du_base64_encode(data.as_ptr(), 4, false, true, &mut output),
+                0
+            );
+            assert_eq!(from_c_str!(output).unwrap(), "Zm9vYg==");
+            du_dispose(output);
+            assert_eq!(
+                du_base64_encode(data.as_ptr(), 4, true, false, &mut output),
+                0
+            );
+            assert_eq!(from_c_str!(output).unwrap(), "Zm9vYg");
+            du_dispose(output);
+        }
+    }
+
+    #[test]
+    fn base64_decode() {
+        unsafe {
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut size: size_t = 0;
+            assert_eq!(
+                du_base64_decode(ptr::null(), false, &mut output, &mut size),
+                -1
+            );
+            assert_eq!(
+                du_base64_decode(
+                    to_c_str!("Zm9vYg==").unwrap().as_ptr(),
+                    false,
+                    ptr::null_mut(),
+                    &mut size
+                ),
+                -1
+            );
+            assert_eq!(
+                du_base64_decode(
+                    to_c_str!("Zm9v*g==").unwrap().as_ptr(),
+                    false,
+                    &mut output,
+                    &mut size
+                ),
+                -2
+            );
+            assert_eq!(
+                du_base64_decode(
+                    to_c_str!("Zm9vYvv_").unwrap().as_ptr(),
+                    false,
+                    &mut output,
+                    &mut size
+                ),
+                -2
+            );
+            assert_eq!(
+                du_base64_decode(
+                    to_c_str!("Zm9vYvv_").unwrap().as_ptr(),
+                    true,
+                    &mut output,
+                    &mut size
+                ),
+                0
+            );
+            assert_eq!(std::slice::from_raw_parts(output, size), b"foob\xfb\xff");
+            du_dispose_bytes(output, size);
+            for encoded in &["Zm9vYg==", "Zm9vYg"] {
+                assert_eq!(
+                    du_base64_decode(
+                        to_c_str!(*encoded).unwrap().as_ptr(),
+                        false,
+                        &mut output,
+                        &mut size
+                    ),
+                    0
+                );
+                assert_eq!(std::slice::from_raw_parts(output, size), b"foob");
+                du_dispose_bytes(output, size);
+            }
+            for encoded in &["Zm9vYg=", "Zm9vYg===", "Zm9v=Yg="] {
+                assert_eq!(
+                    du_base64_decode(
+                        to_c_str!(*encoded).unwrap().as_ptr(),
+                        false,
+                        &mut output,
+                        &mut size
+                    ),
+                    -2
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn base32_encode() {
+        unsafe {
+            let mut output: *mut c_char = ptr::null_mut();
+            let data = b"foobar";
+            assert_eq!(du_base32_encode(ptr::null(), 0, true, &mut output), -1);
+            assert_eq!(
+                du_base32_encode(data.as_ptr(), data.len(), true, ptr::null_mut()),
+                -1
+            );
+            assert_eq!(
+                du_base32_encode(data.as_ptr(), data.len(), true, &mut output),
+                0
+            );
+            assert_eq!(from_c_str!(output).unwrap(), "MZXW6YTBOI======");
+            du_dispose(output);
+            assert_eq!(
+                du_base32_encode(data.as_ptr(), data.len(), false, &mut output),
+                0
+            );
+            assert_eq!(from_c_str!(output).unwrap(), "MZXW6YTBOI");
+            du_dispose(output);
+        }
+    }
+
+    #[test]
+    fn base32_decode() {
+        unsafe {
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut size: size_t = 0;
+            assert_eq!(du_base32_decode(ptr::null(), &mut output, &mut size), -1);
+            assert_eq!(
+                du_base32_decode(
+                    to_c_str!("MZXW6YTBOI").unwrap().as_ptr(),
+                    &mut output,
+                    ptr::null_mut()
+                ),
+                -1
+            );
+            assert_eq!(
+                du_base32_decode(
+                    to_c_str!("MZXW6YTBO1").unwrap().as_ptr(),
+                    &mut output,
+                    &mut size
+                ),
+                -2
+            );
+            for encoded in &["MZXW6YTBOI======", "MZXW6YTBOI"] {
+                assert_eq!(
+                    du_base32_decode(
+                        to_c_str!(*encoded).unwrap().as_ptr(),
+                        &mut output,
+                        &mut size
+                    ),
+                    0
+                );
+                assert_eq!(std::slice::from_raw_parts(output, size), b"foobar");
+                du_dispose_bytes(output, size);
+            }
+            for encoded in &["MZXW6YTBOI=", "MZXW6YTBOI=======", "MZXW6YTBOI==="] {
+                assert_eq!(
+                    du_base32_decode(
+                        to_c_str!(*encoded).unwrap().as_ptr(),
+                        &mut output,
+                        &mut size
+                    ),
+                    -2
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hex_encode() {
+        unsafe {
+            let mut output: *mut c_char = ptr::null_mut();
+            let data = b"\x00\xab\xcdz";
+            assert_eq!(du_hex_encode(ptr::null(), 0, &mut output), -1);
+            assert_eq!(
+                du_hex_encode(data.as_ptr(), data.len(), ptr::null_mut()),
+                -1
+            );
+            assert_eq!(du_hex_encode(data.as_ptr(), data.len(), &mut output), 0);
+            assert_eq!(from_c_str!(output).unwrap(), "00abcd7a");
+            du_dispose(output);
+        }
+    }
+
+    #[test]
+    fn hex_decode() {
+        unsafe {
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut size: size_t = 0;
+            assert_eq!(du_hex_decode(ptr::null(), &mut output, &mut size), -1);
+            assert_eq!(
+                du_hex_decode(
+                    to_c_str!("00abcd7a").unwrap().as_ptr(),
+                    ptr::null_mut(),
+                    &mut size
+                ),
+                -1
+            );
+            assert_eq!(
+                du_hex_decode(to_c_str!("00abc").unwrap().as_ptr(), &mut output, &mut size),
+                -2
+            );
+            for encoded in &["00abcd7a", "00ABCD7A"] {
+                assert_eq!(
+                    du_hex_decode(
+                        to_c_str!(*encoded).unwrap().as_ptr(),
+                        &mut output,
+                        &mut size
+                    ),
+                    0
+                );
+                assert_eq!(std::slice::from_raw_parts(output, size), b"\x00\xab\xcdz");
+                du_dispose_bytes(output, size);
+            }
+        }
+    }
+
+    #[test]
+    fn hotp() {
+        unsafe {
+            let secret = b"12345678901234567890";
+            let code: [c_char; 9] = [0; 9];
+            assert_eq!(
+                du_hotp(
+                    ptr::null(),
+                    0,
+                    0,
+                    6,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr() as *mut c_char,
+                    code.len()
+                ),
+                -1
+            );
+            assert_eq!(
+                du_hotp(
+                    secret.as_ptr(),
+                    secret.len(),
+                    0,
+                    5,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr() as *mut c_char,
+                    code.len()
+                ),
+                -1
+            );
+            assert_eq!(
+                du_hotp(
+                    secret.as_ptr(),
+                    secret.len(),
+                    0,
+                    6,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    ptr::null_mut(),
+                    code.len()
+                ),
+                -1
+            );
+            for (counter, expected) in ["755224", "287082", "359152", "969429"].iter().enumerate() {
+                assert_eq!(
+                    du_hotp(
+                        secret.as_ptr(),
+                        secret.len(),
+                        counter as u64,
+                        6,
+                        DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                        code.as_ptr() as *mut c_char,
+                        code.len()
+                    ),
+                    0
+                );
+                assert_eq!(from_c_str!(code.as_ptr()).unwrap(), *expected);
+            }
+        }
+    }
+
+    #[test]
+    fn hotp_verify() {
+        unsafe {
+            let secret = b"12345678901234567890";
+            let mut matched: u64 = 0;
+            assert_eq!(
+                du_hotp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    0,
+                    0,
+                    6,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    ptr::null(),
+                    &mut matched
+                ),
+                -1
+            );
+            assert_eq!(
+                du_hotp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    0,
+                    2,
+                    6,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    to_c_str!("969429").unwrap().as_ptr(),
+                    &mut matched
+                ),
+                -2
+            );
+            assert_eq!(
+                du_hotp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    0,
+                    3,
+                    6,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    to_c_str!("969429").unwrap().as_ptr(),
+                    &mut matched
+                ),
+                0
+            );
+            assert_eq!(matched, 3);
+        }
+    }
+
+    #[test]
+    fn totp() {
+        unsafe {
+            let secret_sha1 = b"12345678901234567890";
+            let secret_sha256 = b"12345678901234567890123456789012";
+            let code: [c_char; 9] = [0; 9];
+            assert_eq!(
+                du_totp(
+                    secret_sha1.as_ptr(),
+                    secret_sha1.len(),
+                    59,
+                    0,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr() as *mut c_char,
+                    code.len()
+                ),
+                -1
+            );
+            assert_eq!(
+                du_totp(
+                    secret_sha1.as_ptr(),
+                    secret_sha1.len(),
+                    59,
+                    30,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr() as *mut c_char,
+                    code.len()
+                ),
+                0
+            );
+            assert_eq!(from_c_str!(code.as_ptr()).unwrap(), "94287082");
+            assert_eq!(
+                du_totp(
+                    secret_sha1.as_ptr(),
+                    secret_sha1.len(),
+                    1111111109,
+                    30,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr() as *mut c_char,
+                    code.len()
+                ),
+                0
+            );
+            assert_eq!(from_c_str!(code.as_ptr()).unwrap(), "07081804");
+            assert_eq!(
+                du_totp(
+                    secret_sha256.as_ptr(),
+                    secret_sha256.len(),
+                    59,
+                    30,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA256,
+                    code.as_ptr() as *mut c_char,
+                    code.len()
+                ),
+                0
+            );
+            assert_eq!(from_c_str!(code.as_ptr()).unwrap(), "46119246");
+            assert_eq!(
+                du_totp(
+                    secret_sha256.as_ptr(),
+                    secret_sha256.len(),
+                    20000000000,
+                    30,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA256,
+                    code.as_ptr() as *mut c_char,
+                    code.len()
+                ),
+                0
+            );
+            assert_eq!(from_c_str!(code.as_ptr()).unwrap(), "77737706");
+        }
+    }
+
+    #[test]
+    fn totp_verify() {
+        unsafe {
+            let secret = b"12345678901234567890";
+            let code = to_c_str!("07081804").unwrap();
+            assert_eq!(
+                du_totp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    1111111109,
+                    0,
+                    1,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr()
+                ),
+                -1
+            );
+            assert_eq!(
+                du_totp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    1111111109,
+                    30,
+                    0,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr()
+                ),
+                0
+            );
+            assert_eq!(
+                du_totp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    1111111109 + 30,
+                    30,
+                    0,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr()
+                ),
+                -2
+            );
+            assert_eq!(
+                du_totp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    1111111109 + 30,
+                    30,
+                    1,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr()
+                ),
+                0
+            );
+            assert_eq!(
+                du_totp_verify(
+                    secret.as_ptr(),
+                    secret.len(),
+                    1111111109 - 30,
+                    30,
+                    1,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    code.as_ptr()
+                ),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn totp_uri() {
+        unsafe {
+            let secret = b"12345678901234567890";
+            let mut output: *mut c_char = ptr::null_mut();
+            assert_eq!(
+                du_totp_uri(
+                    secret.as_ptr(),
+                    secret.len(),
+                    ptr::null(),
+                    ptr::null(),
+                    30,
+                    6,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    &mut output
+                ),
+                -1
+            );
+            assert_eq!(
+                du_totp_uri(
+                    secret.as_ptr(),
+                    secret.len(),
+                    to_c_str!("admin@duall.com.br").unwrap().as_ptr(),
+                    to_c_str!("Duall ERP").unwrap().as_ptr(),
+                    30,
+                    6,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA1,
+                    &mut output
+                ),
+                0
+            );
+            assert_eq!(
+                from_c_str!(output).unwrap(),
+                "otpauth://totp/Duall%20ERP:admin%40duall.com.br?\
+                 secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Duall%20ERP&\
+                 algorithm=SHA1&digits=6&period=30"
+            );
+            du_dispose(output);
+            assert_eq!(
+                du_hotp_uri(
+                    secret.as_ptr(),
+                    secret.len(),
+                    to_c_str!("admin").unwrap().as_ptr(),
+                    ptr::null(),
+                    5,
+                    8,
+                    DU_OTP_ALGORITHM::DU_OTP_SHA256,
+                    &mut output
+                ),
+                0
+            );
+            assert_eq!(
+                from_c_str!(output).unwrap(),
+                "otpauth://hotp/admin?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&\
+                 algorithm=SHA256&digits=8&counter=5"
+            );
+            du_dispose(output);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn spawn_options() -> DU_SPAWN_OPTIONS {
+        DU_SPAWN_OPTIONS {
+            workdir: ptr::null(),
+            hidden: false,
+            new_process_group: false,
+            new_console: false,
+            detached: false,
+            inherit_handles: true,
+            kill_tree: false,
+            kill_with_parent: false,
+            stdout: ptr::null(),
+            stderr: ptr::null(),
+            credentials: ptr::null(),
+            limits: ptr::null(),
+        }
+    }
+
+    #[test]
+    fn spawn() {
+        unsafe {
+            let mut code: c_int = 0;
+            assert_eq!(
+                du_spawn(
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    &mut code
+                ),
+                -1
+            );
+            assert_eq!(
+                du_spawn(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    &mut code
+                ),
+                -2
+            );
+            assert_eq!(
+                du_spawn(
+                    to_c_str!("echo").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    &mut code
+                ),
+                0
+            );
+            assert_eq!(code, 0);
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            assert_eq!(
+                du_spawn_ex(
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    0,
+                    None,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                -1
+            );
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    0,
+                    None,
+                    ptr::null_mut(),
+                    ptr::null_mut()
+                ),
+                -1
+            );
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    0,
+                    None,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                -2
+            );
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("echo").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    0,
+                    None,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                0
+            );
+            assert_eq!(status.kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert_eq!(status.code, 0);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn spawn_status() {
+        unsafe {
+            let mut exitcode: c_int = -1;
+            for (script, code) in [("exit 3", 3), ("kill -9 $$", 128 + 9)] {
+                let args: [*const c_char; 3] = [
+                    CString::new("-c").unwrap().into_raw(),
+                    CString::new(script).unwrap().into_raw(),
+                    ptr::null(),
+                ];
+                assert_eq!(
+                    du_spawn(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        ptr::null(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        true,
+                        &mut exitcode
+                    ),
+                    0
+                );
+                assert_eq!(exitcode, code);
+                drop(CString::from_raw(args[0] as *mut c_char));
+                drop(CString::from_raw(args[1] as *mut c_char));
+            }
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            let args: [*const c_char; 2] = [CString::new("5").unwrap().into_raw(), ptr::null()];
+            let started = std::time::Instant::now();
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("sleep").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    100,
+                    None,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                -4
+            );
+            assert!(started.elapsed().as_secs() < 5);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            for (script, kind, code) in [
+                ("exit 3", DU_EXIT_KIND::DU_EXIT_CODE, 3),
+                ("kill -9 $$", DU_EXIT_KIND::DU_EXIT_SIGNAL, 9),
+            ] {
+                let args: [*const c_char; 3] = [
+                    CString::new("-c").unwrap().into_raw(),
+                    CString::new(script).unwrap().into_raw(),
+                    ptr::null(),
+                ];
+                assert_eq!(
+                    du_spawn_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        ptr::null(),
+                        true,
+                        0,
+                        None,
+                        ptr::null_mut(),
+                        &mut status
+                    ),
+                    0
+                );
+                assert_eq!(status.kind, kind);
+                assert_eq!(status.code, code);
+                drop(CString::from_raw(args[0] as *mut c_char));
+                drop(CString::from_raw(args[1] as *mut c_char));
+            }
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("./LICENSE").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    true,
+                    0,
+                    None,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                -3
+            );
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn spawn_redirect() {
+        unsafe {
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            let log = std::env::temp_dir().join("libduallutils-redirect.log");
+            let path = to_c_str!(log.to_str().unwrap()).unwrap();
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("echo out; echo err >&2").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            for mode in [
+                DU_REDIRECT_MODE::DU_REDIRECT_TRUNCATE,
+                DU_REDIRECT_MODE::DU_REDIRECT_APPEND,
+            ] {
+                let stdout = DU_REDIRECT {
+                    mode,
+                    path: path.as_ptr(),
+                };
+                let stderr = DU_REDIRECT {
+                    mode: DU_REDIRECT_MODE::DU_REDIRECT_STDOUT,
+                    path: ptr::null(),
+                };
+                assert_eq!(
+                    du_spawn_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        &DU_SPAWN_OPTIONS {
+                            stdout: &stdout,
+                            stderr: &stderr,
+                            ..spawn_options()
+                        },
+                        true,
+                        0,
+                        None,
+                        ptr::null_mut(),
+                        &mut status
+                    ),
+                    0
+                );
+            }
+            assert_eq!(fs::read_to_string(&log).unwrap(), "out\nerr\nout\nerr\n");
+            let file = DU_REDIRECT {
+                mode: DU_REDIRECT_MODE::DU_REDIRECT_TRUNCATE,
+                path: path.as_ptr(),
+            };
+            let null = DU_REDIRECT {
+                mode: DU_REDIRECT_MODE::DU_REDIRECT_NULL,
+                path: ptr::null(),
+            };
+            for (stdout, stderr, expected) in [(&file, &null, "out\n"), (&null, &file, "err\n")] {
+                assert_eq!(
+                    du_spawn_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        &DU_SPAWN_OPTIONS {
+                            stdout,
+                            stderr,
+                            ..spawn_options()
+                        },
+                        true,
+                        0,
+                        None,
+                        ptr::null_mut(),
+                        &mut status
+                    ),
+                    0
+                );
+                assert_eq!(fs::read_to_string(&log).unwrap(), expected);
+            }
+            fs::remove_file(&log).unwrap();
+            let invalid = DU_REDIRECT {
+                mode: DU_REDIRECT_MODE::DU_REDIRECT_APPEND,
+                path: ptr::null(),
+            };
+            let merged = DU_REDIRECT {
+                mode: DU_REDIRECT_MODE::DU_REDIRECT_STDOUT,
+                path: ptr::null(),
+            };
+            let missing = DU_REDIRECT {
+                mode: DU_REDIRECT_MODE::DU_REDIRECT_TRUNCATE,
+                path: to_c_str!("blah/blah.log").unwrap().into_raw(),
+            };
+            for (stdout, code) in [(&invalid, -1), (&merged, -1), (&missing, -5)] {
+                assert_eq!(
+                    du_spawn_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        &DU_SPAWN_OPTIONS {
+                            stdout,
+                            ..spawn_options()
+                        },
+                        true,
+                        0,
+                        None,
+                        ptr::null_mut(),
+                        &mut status
+                    ),
+                    code
+                );
+            }
+            drop(CString::from_raw(missing.path as *mut c_char));
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn spawn_credentials() {
+        unsafe {
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            let log = std::env::temp_dir().join("libduallutils-credentials.log");
+            let stdout = DU_REDIRECT {
+                mode: DU_REDIRECT_MODE::DU_REDIRECT_TRUNCATE,
+                path: to_c_str!(log.to_str().unwrap()).unwrap().into_raw(),
+            };
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("id -u; id -g; id -G").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let groups: [*const c_char; 2] =
+                [CString::new("root").unwrap().into_raw(), ptr::null()];
+            let nobody = to_c_str!("nobody").unwrap();
+            let numeric = to_c_str!("65534").unwrap();
+            let nogroup = to_c_str!("nogroup").unwrap();
+            let blah = to_c_str!("blah blah").unwrap();
+            let invalid: [*const c_char; 2] = [b"\xff\0".as_ptr() as *const c_char, ptr::null()];
+            let root = libc::geteuid() == 0;
+            for (credentials, code, expected) in [
+                (
+                    DU_CREDENTIALS {
+                        user: nobody.as_ptr(),
+                        group: ptr::null(),
+                        groups: ptr::null(),
+                    },
+                    if root { 0 } else { -7 },
+                    "65534\n65534\n65534\n",
+                ),
+                (
+                    DU_CREDENTIALS {
+                        user: numeric.as_ptr(),
+                        group: nogroup.as_ptr(),
+                        groups: groups.as_ptr(),
+                    },
+                    if root { 0 } else { -7 },
+                    "65534\n65534\n65534 0\n",
+                ),
+                (
+                    DU_CREDENTIALS {
+                        user: blah.as_ptr(),
+                        group: ptr::null(),
+                        groups: ptr::null(),
+                    },
+                    -6,
+                    "",
+                ),
+                (
+                    DU_CREDENTIALS {
+                        user: ptr::null(),
+                        group: blah.as_ptr(),
+                        groups: ptr::null(),
+                    },
+                    -6,
+                    "",
+                ),
+                (
+                    DU_CREDENTIALS {
+                        user: invalid[0],
+                        group: ptr::null(),
+                        groups: ptr::null(),
+                    },
+                    -1,
+                    "",
+                ),
+                (
+                    DU_CREDENTIALS {
+                        user: ptr::null(),
+                        group: invalid[0],
+                        groups: ptr::null(),
+                    },
+                    -1,
+                    "",
+                ),
+                (
+                    DU_CREDENTIALS {
+                        user: ptr::null(),
+                        group: ptr::null(),
+                        groups: invalid.as_ptr(),
+                    },
+                    -1,
+                    "",
+                ),
+            ] {
+                fs::remove_file(&log).unwrap_or_default();
+                assert_eq!(
+                    du_spawn_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        &DU_SPAWN_OPTIONS {
+                            stdout: &stdout,
+                            credentials: &credentials,
+                            ..spawn_options()
+                        },
+                        true,
+                        0,
+                        None,
+                        ptr::null_mut(),
+                        &mut status
+                    ),
+                    code
+                );
+                if code == 0 {
+                    assert_eq!(fs::read_to_string(&log).unwrap(), expected);
+                }
+            }
+            fs::remove_file(&log).unwrap_or_default();
+            for arg in [stdout.path, args[0], args[1], groups[0]] {
+                drop(CString::from_raw(arg as *mut c_char));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn spawn_limits() {
+        unsafe {
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            let log = std::env::temp_dir().join("libduallutils-limits.log");
+            let stdout = DU_REDIRECT {
+                mode: DU_REDIRECT_MODE::DU_REDIRECT_TRUNCATE,
+                path: to_c_str!(log.to_str().unwrap()).unwrap().into_raw(),
+            };
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("umask; ulimit -n; ulimit -v; nice")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            let limits = DU_LIMITS {
+                address_space: 512 * 1024 * 1024,
+                cpu_time: 0,
+                open_files: 64,
+                nice: 5,
+                umask: 0o027,
+            };
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &DU_SPAWN_OPTIONS {
+                        stdout: &stdout,
+                        limits: &limits,
+                        ..spawn_options()
+                    },
+                    true,
+                    0,
+                    None,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                0
+            );
+            assert_eq!(fs::read_to_string(&log).unwrap(), "0027\n64\n524288\n5\n");
+            // Lowering the nice value inherited from the spawning thread requires privileges.
+            if libc::geteuid() == 0 {
+                libc::setpriority(libc::PRIO_PROCESS, 0, 3);
+                for (nice, expected) in [(0, "0"), (100, "3")] {
+                    let limits = DU_LIMITS {
+                        address_space: 0,
+                        cpu_time: 0,
+                        open_files: 0,
+                        nice,
+                        umask: -1,
+                    };
+                    assert_eq!(
+                        du_spawn_ex(
+                            to_c_str!("sh").unwrap().as_ptr(),
+                            args.as_ptr(),
+                            ptr::null(),
+                            ptr::null(),
+                            &DU_SPAWN_OPTIONS {
+                                stdout: &stdout,
+                                limits: &limits,
+                                ..spawn_options()
+                            },
+                            true,
+                            0,
+                            None,
+                            ptr::null_mut(),
+                            &mut status
+                        ),
+                        0
+                    );
+                    let output = fs::read_to_string(&log).unwrap();
+                    assert_eq!(output.lines().last(), Some(expected));
+                }
+                libc::setpriority(libc::PRIO_PROCESS, 0, 0);
+                let limits = DU_LIMITS {
+                    address_space: 0,
+                    cpu_time: 0,
+                    open_files: 0,
+                    nice: -5,
+                    umask: -1,
+                };
+                let credentials = DU_CREDENTIALS {
+                    user: to_c_str!("nobody").unwrap().into_raw(),
+                    group: ptr::null(),
+                    groups: ptr::null(),
+                };
+                assert_eq!(
+                    du_spawn_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        &DU_SPAWN_OPTIONS {
+                            stdout: &stdout,
+                            credentials: &credentials,
+                            limits: &limits,
+                            ..spawn_options()
+                        },
+                        true,
+                        0,
+                        None,
+                        ptr::null_mut(),
+                        &mut status
+                    ),
+                    0
+                );
+                let output = fs::read_to_string(&log).unwrap();
+                assert_eq!(output.lines().last(), Some("-5"));
+                drop(CString::from_raw(credentials.user as *mut c_char));
+            }
+            fs::remove_file(&log).unwrap();
+            drop(CString::from_raw(stdout.path as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            let args: [*const c_char; 3] = [
+                args[0],
+                CString::new("while :; do :; done").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let limits = DU_LIMITS {
+                address_space: 0,
+                cpu_time: 1,
+                open_files: 0,
+                nice: 100,
+                umask: -1,
+            };
+            let started = std::time::Instant::now();
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &DU_SPAWN_OPTIONS {
+                        limits: &limits,
+                        ..spawn_options()
+                    },
+                    true,
+                    10000,
+                    None,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                0
+            );
+            assert!(started.elapsed().as_secs() < 10);
+            assert_ne!(status.kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert!([libc::SIGXCPU, libc::SIGKILL].contains(&status.code));
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    extern "C" fn exited(cls: *mut c_void, pid: c_uint, status: DU_EXIT_STATUS) {
+        let exits = unsafe { &*(cls as *const std::sync::Mutex<Vec<(c_uint, DU_EXIT_STATUS)>>) };
+        exits.lock().unwrap().push((pid, status));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn spawn_exit_callback() {
+        unsafe {
+            let exits = std::sync::Mutex::new(Vec::<(c_uint, DU_EXIT_STATUS)>::new());
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("sleep 0.1; exit 3").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            assert_eq!(
+                du_spawn_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    false,
+                    0,
+                    Some(exited),
+                    &exits as *const _ as *mut c_void,
+                    &mut status
+                ),
+                0
+            );
+            assert!(exits.lock().unwrap().is_empty());
+            let started = std::time::Instant::now();
+            while exits.lock().unwrap().is_empty() && started.elapsed().as_secs() < 5 {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            let exits = exits.lock().unwrap();
+            assert_eq!(exits.len(), 1);
+            let (pid, status) = &exits[0];
+            assert_eq!(status.kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert_eq!(status.code, 3);
+            assert!(!Path::new(&format!("/proc/{}", pid)).exists());
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn spawn_elevated() {
+        unsafe {
+            use std::os::unix::fs::PermissionsExt;
+            let dir = std::env::temp_dir().join("libduallutils-elevation");
+            fs::remove_dir_all(&dir).unwrap_or_default();
+            fs::create_dir_all(dir.join("empty")).unwrap();
+            for (name, script) in [
+                (
+                    "pkexec",
+                    "#!/bin/sh\ncase \"$1\" in cancel) exit 126;; deny) exit 127;; esac\nexec \"$@\"\n",
+                ),
+                (
+                    "sudo",
+                    "#!/bin/sh\n[ \"$1\" = -n ] || exit 2\necho \"$2\" >> \"$0.log\"\n\
+                     [ \"$3\" = deny ] || [ \"$4\" = deny ] && exit 1\n\
+                     if [ \"$2\" = -l ]; then exit 0; fi\n\
+                     shift 2\nexec \"$@\"\n",
+                ),
+            ] {
+                let helper = dir.join(name);
+                fs::write(&helper, script).unwrap();
+                fs::set_permissions(&helper, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+            let path = CString::new(format!("PATH={}:/usr/bin:/bin", dir.display())).unwrap();
+            let envs: [*const c_char; 2] = [path.as_ptr(), ptr::null()];
+            let empty = CString::new(format!("PATH={}", dir.join("empty").display())).unwrap();
+            let no_helpers: [*const c_char; 2] = [empty.as_ptr(), ptr::null()];
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("exit 5").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let mut exit_status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            let status: *mut DU_EXIT_STATUS = &mut exit_status;
+            let elevate = |program: &str,
+                           envs: *const *const c_char,
+                           helper: DU_ELEVATION,
+                           helper_path: *const c_char| {
+                let program = CString::new(program).unwrap();
+                du_spawn_elevated(
+                    program.as_ptr(),
+                    ptr::null(),
+                    args.as_ptr(),
+                    envs,
+                    ptr::null(),
+                    helper,
+                    helper_path,
+                    0,
+                    status,
+                )
+            };
+            assert_eq!(
+                elevate(
+                    "sh",
+                    envs.as_ptr(),
+                    DU_ELEVATION::DU_ELEVATION_CUSTOM,
+                    ptr::null()
+                ),
+                -1
+            );
+            for helper in [
+                DU_ELEVATION::DU_ELEVATION_PKEXEC,
+                DU_ELEVATION::DU_ELEVATION_SUDO,
+            ] {
+                assert_eq!(elevate("sh", no_helpers.as_ptr(), helper, ptr::null()), -2);
+                assert_eq!(elevate("deny", envs.as_ptr(), helper, ptr::null()), -6);
+                assert_eq!(elevate("sh", envs.as_ptr(), helper, ptr::null()), 0);
+            }
+            assert_eq!(
+                fs::read_to_string(dir.join("sudo.log")).unwrap(),
+                "--\n-l\n--\n"
+            );
+            assert_eq!(
+                elevate(
+                    "cancel",
+                    envs.as_ptr(),
+                    DU_ELEVATION::DU_ELEVATION_PKEXEC,
+                    ptr::null()
+                ),
+                -5
+            );
+            assert_eq!((*status).code, 126);
+            let mut helper_path = to_c_str!("/blah/blah").unwrap();
+            assert_eq!(
+                elevate(
+                    "sh",
+                    envs.as_ptr(),
+                    DU_ELEVATION::DU_ELEVATION_CUSTOM,
+                    helper_path.as_ptr()
+                ),
+                -2
+            );
+            helper_path = CString::new(dir.join("pkexec").to_str().unwrap()).unwrap();
+            assert_eq!(
+                elevate(
+                    "cancel",
+                    no_helpers.as_ptr(),
+                    DU_ELEVATION::DU_ELEVATION_CUSTOM,
+                    helper_path.as_ptr()
+                ),
+                -5
+            );
+            assert_eq!(
+                elevate(
+                    "sh",
+                    envs.as_ptr(),
+                    DU_ELEVATION::DU_ELEVATION_CUSTOM,
+                    helper_path.as_ptr()
+                ),
+                0
+            );
+            assert_eq!((*status).kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert_eq!((*status).code, 5);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn spawn_detached() {
+        unsafe {
+            let mut pid: c_uint = 0;
+            assert_eq!(
+                du_spawn_detached(
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    &mut pid
+                ),
+                -1
+            );
+            assert_eq!(
+                du_spawn_detached(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    &mut pid
+                ),
+                -2
+            );
+            let fd = libc::dup(0);
+            let log = std::env::temp_dir().join("libduallutils-detached.log");
+            fs::remove_file(&log).unwrap_or_default();
+            let script = format!(
+                "s=$(cut -d' ' -f6 /proc/$$/stat); \
+                 [ -e /proc/$$/fd/{} ] && f=open || f=closed; \
+                 echo \"$s $f\" > {}.tmp && mv {}.tmp {}",
+                fd,
+                log.display(),
+                log.display(),
+                log.display()
+            );
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new(script).unwrap().into_raw(),
+                ptr::null(),
+            ];
+            assert_eq!(
+                du_spawn_detached(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    ptr::null(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &mut pid
+                ),
+                0
+            );
+            libc::close(fd);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            let started = std::time::Instant::now();
+            while !log.exists() && started.elapsed().as_secs() < 5 {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            assert_eq!(
+                fs::read_to_string(&log).unwrap(),
+                format!("{} closed\n", pid)
+            );
+            fs::remove_file(&log).unwrap();
+        }
+    }
+
+    #[test]
+    fn execute() {
+        unsafe {
+            let mut output: *mut c_char = ptr::null_mut();
+            let mut error: *mut c_char = ptr::null_mut();
+            let mut code: c_int = 0;
+            assert_eq!(
+                du_execute(
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    &mut output,
+                    &mut error,
+                    &mut code
+                ),
+                -1
+            );
+            assert_eq!(
+                du_execute(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    &mut output,
+                    &mut error,
+                    &mut code
+                ),
+                -2
+            );
+            let args: [*const c_char; 2] =
+                [CString::new("My test").unwrap().into_raw(), ptr::null()];
+            assert_eq!(
+                du_execute(
+                    to_c_str!("echo").unwrap().as_ptr(),
+                    ptr::null(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    &mut output,
+                    &mut error,
+                    &mut code
+                ),
+                0
+            );
+            drop(CString::from_raw(args[0] as *mut c_char));
+            assert_eq!(code, 0);
+            assert_eq!(from_c_str!(output).unwrap().trim(), "My test");
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut error: *mut u8 = ptr::null_mut();
+            let mut error_size: size_t = 0;
+            let mut truncated = true;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            assert_eq!(
+                du_execute_ex(
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                -1
+            );
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut()
+                ),
+                -1
+            );
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                -2
+            );
+            let args: [*const c_char; 2] =
+                [CString::new("My test").unwrap().into_raw(), ptr::null()];
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("echo").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                0
+            );
+            drop(CString::from_raw(args[0] as *mut c_char));
+            assert_eq!(status.kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert_eq!(status.code, 0);
+            assert_eq!(
+                std::slice::from_raw_parts(output, output_size),
+                b"My test\n"
+            );
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn execute_status() {
+        unsafe {
+            let mut stdout: *mut c_char = ptr::null_mut();
+            let mut stderr: *mut c_char = ptr::null_mut();
+            let mut exitcode: c_int = -1;
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("echo out; echo err >&2; kill -9 $$")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            assert_eq!(
+                du_execute(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    ptr::null(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    &mut stdout,
+                    &mut stderr,
+                    &mut exitcode
+                ),
+                0
+            );
+            assert_eq!(from_c_str!(stdout).unwrap(), "out\n");
+            assert_eq!(from_c_str!(stderr).unwrap(), "err\n");
+            assert_eq!(exitcode, 128 + 9);
+            du_dispose(stdout);
+            du_dispose(stderr);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut error: *mut u8 = ptr::null_mut();
+            let mut error_size: size_t = 0;
+            let mut truncated = true;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("echo partial; echo failure >&2; sleep 5")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            let started = std::time::Instant::now();
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    500,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                -4
+            );
+            assert!(started.elapsed().as_secs() < 5);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            assert_eq!(
+                std::slice::from_raw_parts(output, output_size),
+                b"partial\n"
+            );
+            assert_eq!(std::slice::from_raw_parts(error, error_size), b"failure\n");
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn execute_output() {
+        unsafe {
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut error: *mut u8 = ptr::null_mut();
+            let mut error_size: size_t = 0;
+            let mut truncated = true;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            let input = "a".repeat(1024 * 1024);
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("cat").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    input.as_ptr(),
+                    input.len(),
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                0
+            );
+            assert_eq!(status.kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert_eq!(status.code, 0);
+            assert_eq!(
+                std::slice::from_raw_parts(output, output_size),
+                input.as_bytes()
+            );
+            assert_eq!(truncated, false);
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("printf '\\000\\377\\207\\343o'")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                0
+            );
+            assert_eq!(
+                std::slice::from_raw_parts(output, output_size),
+                b"\x00\xff\x87\xe3o"
+            );
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            for (encoding, expected) in [
+                ("CP850", "\0\u{a0}çÒo"),
+                ("cp1252", "\0ÿ‡ão"),
+                ("ISO-8859-1", "\0ÿ\u{87}ão"),
+                ("UTF-8", "\0\u{fffd}\u{fffd}\u{fffd}o"),
+            ] {
+                assert_eq!(
+                    du_execute_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        ptr::null(),
+                        ptr::null(),
+                        0,
+                        0,
+                        0,
+                        DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                        to_c_str!(encoding).unwrap().as_ptr(),
+                        &mut output,
+                        &mut output_size,
+                        &mut error,
+                        &mut error_size,
+                        &mut truncated,
+                        &mut status
+                    ),
+                    0
+                );
+                assert_eq!(
+                    std::slice::from_raw_parts(output, output_size),
+                    expected.as_bytes()
+                );
+                du_dispose_bytes(output, output_size);
+                du_dispose_bytes(error, error_size);
+            }
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    to_c_str!("EBCDIC").unwrap().as_ptr(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                -1
+            );
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("yes | head -c 100000; echo failure >&2")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    1000,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                0
+            );
+            assert_eq!(truncated, true);
+            assert_eq!(status.code, 0);
+            assert_eq!(output_size, 1000);
+            assert_eq!(std::slice::from_raw_parts(error, error_size), b"failure\n");
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            let started = std::time::Instant::now();
+            assert_eq!(
+                du_execute_ex(
+                    to_c_str!("yes").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    1000,
+                    DU_OVERFLOW::DU_OVERFLOW_KILL,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
+                -5
+            );
+            assert!(started.elapsed().as_secs() < 5);
+            assert_eq!(truncated, true);
+            assert_eq!(output_size, 1000);
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+        }
+    }
 
-/// Deletes one or more files matching their paths against Unix shell style patterns.
-///
-/// # Arguments
-///
-/// * `[in] pattern` - Pattern in Unix shell style.
-///
-/// # Returns
-///
-/// * `0` - Success.
-/// * `-1` - Invalid argument.
-/// * `-2` - Not found.
-/// * `-3` - OS error.
-#[no_mangle]
-pub unsafe extern "C" fn du_deltree(pattern: *const c_char) -> c_int {
-    if pattern.is_null() {
-        return -1;
-    }
-    let mut found: c_int = -2;
-    for entry in glob(from_c_str!(pattern).unwrap()).unwrap() {
-        match entry {
-            Ok(path) => {
-                if path.is_dir() {
-                    fs::remove_dir_all(path).unwrap_or_default();
-                } else {
-                    fs::remove_file(path).unwrap_or_default();
-                }
-                found = 0;
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn execute_env() {
+        unsafe {
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut error: *mut u8 = ptr::null_mut();
+            let mut error_size: size_t = 0;
+            let mut truncated = true;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            std::env::set_var("DU_INHERITED", "kept");
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("echo \"$FOO|$DU_INHERITED\"")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            let envs: [*const c_char; 2] =
+                [CString::new("FOO=bar").unwrap().into_raw(), ptr::null()];
+            let inherit: [*const c_char; 2] = [
+                CString::new("DU_INHERITED").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let remove = inherit;
+            for (env_options, expected) in [
+                (
+                    DU_ENV_OPTIONS {
+                        clear: false,
+                        inherit: ptr::null(),
+                        remove: ptr::null(),
+                    },
+                    "bar|kept\n",
+                ),
+                (
+                    DU_ENV_OPTIONS {
+                        clear: true,
+                        inherit: ptr::null(),
+                        remove: ptr::null(),
+                    },
+                    "bar|\n",
+                ),
+                (
+                    DU_ENV_OPTIONS {
+                        clear: true,
+                        inherit: inherit.as_ptr(),
+                        remove: ptr::null(),
+                    },
+                    "bar|kept\n",
+                ),
+                (
+                    DU_ENV_OPTIONS {
+                        clear: false,
+                        inherit: ptr::null(),
+                        remove: remove.as_ptr(),
+                    },
+                    "bar|\n",
+                ),
+            ] {
+                assert_eq!(
+                    du_execute_ex(
+                        to_c_str!("/bin/sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        envs.as_ptr(),
+                        &env_options,
+                        ptr::null(),
+                        ptr::null(),
+                        0,
+                        0,
+                        0,
+                        DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                        ptr::null(),
+                        &mut output,
+                        &mut output_size,
+                        &mut error,
+                        &mut error_size,
+                        &mut truncated,
+                        &mut status
+                    ),
+                    0
+                );
+                assert_eq!(
+                    std::slice::from_raw_parts(output, output_size),
+                    expected.as_bytes()
+                );
+                du_dispose_bytes(output, output_size);
+                du_dispose_bytes(error, error_size);
+            }
+            std::env::remove_var("DU_INHERITED");
+            for array in [args[0], args[1], envs[0], inherit[0]] {
+                drop(CString::from_raw(array as *mut c_char));
             }
-            Err(_) => return -3,
         }
     }
-    found
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
+    #[cfg(target_os = "linux")]
     #[test]
-    fn version() {
+    fn execute_options() {
         unsafe {
+            let run = |options: *const DU_SPAWN_OPTIONS, fd: c_int| {
+                let mut output: *mut u8 = ptr::null_mut();
+                let mut output_size: size_t = 0;
+                let mut error: *mut u8 = ptr::null_mut();
+                let mut error_size: size_t = 0;
+                let mut status = DU_EXIT_STATUS {
+                    kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                    code: -1,
+                };
+                let script = format!(
+                    "[ $(cut -d' ' -f5 /proc/$$/stat) = $$ ] && g=group || g=-; \
+                     [ $(cut -d' ' -f6 /proc/$$/stat) = $$ ] && s=session || s=-; \
+                     [ -e /proc/$$/fd/{} ] && f=open || f=closed; \
+                     echo \"$g $s $f $(pwd)\"",
+                    fd
+                );
+                let args: [*const c_char; 3] = [
+                    CString::new("-c").unwrap().into_raw(),
+                    CString::new(script).unwrap().into_raw(),
+                    ptr::null(),
+                ];
+                let code = du_execute_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    options,
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    ptr::null_mut(),
+                    &mut status,
+                );
+                drop(CString::from_raw(args[0] as *mut c_char));
+                drop(CString::from_raw(args[1] as *mut c_char));
+                if code != 0 {
+                    return Err(code);
+                }
+                let result =
+                    String::from_utf8(std::slice::from_raw_parts(output, output_size).to_vec())
+                        .unwrap();
+                du_dispose_bytes(output, output_size);
+                du_dispose_bytes(error, error_size);
+                Ok(result)
+            };
+            let workdir = CString::new("/tmp").unwrap();
+            let mut options = DU_SPAWN_OPTIONS {
+                workdir: workdir.as_ptr(),
+                ..spawn_options()
+            };
+            let fd = libc::dup(0);
             assert_eq!(
-                from_c_str!(du_version()).unwrap(),
-                env!("CARGO_PKG_VERSION")
+                run(ptr::null(), fd),
+                Ok(format!(
+                    "- - open {}\n",
+                    std::env::current_dir().unwrap().display()
+                ))
             );
+            assert_eq!(run(&options, fd), Ok("- - open /tmp\n".to_string()));
+            options.new_process_group = true;
+            assert_eq!(run(&options, fd), Ok("group - open /tmp\n".to_string()));
+            options.inherit_handles = false;
+            assert_eq!(run(&options, fd), Ok("group - closed /tmp\n".to_string()));
+            options.hidden = true;
+            assert_eq!(run(&options, fd), Err(-1));
+            options.hidden = false;
+            options.new_console = true;
+            assert_eq!(run(&options, fd), Err(-1));
+            options.new_console = false;
+            options.new_process_group = false;
+            options.detached = true;
+            assert_eq!(
+                run(&options, fd),
+                Ok("group session closed /tmp\n".to_string())
+            );
+            options.kill_with_parent = true;
+            assert_eq!(run(&options, fd), Err(-1));
+            libc::close(fd);
         }
     }
 
+    #[cfg(target_os = "linux")]
     #[test]
-    fn md5() {
+    fn execute_kill_tree() {
         unsafe {
-            let hash: [c_char; 33] = [0; 33];
-            assert_eq!(du_md5(ptr::null(), hash.as_ptr() as *mut c_char, 33), -1);
+            let alive = |pid: &str| {
+                fs::read_to_string(format!("/proc/{}/stat", pid))
+                    .map(|stat| !stat.contains(") Z "))
+                    .unwrap_or(false)
+            };
+            let killed = |pid: &str| {
+                let started = std::time::Instant::now();
+                while alive(pid) && started.elapsed().as_secs() < 5 {
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+                !alive(pid)
+            };
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("sleep 30 & echo $!; wait").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let mut options = DU_SPAWN_OPTIONS { ..spawn_options() };
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
+            for kill_tree in [false, true] {
+                options.kill_tree = kill_tree;
+                let mut output: *mut u8 = ptr::null_mut();
+                let mut output_size: size_t = 0;
+                let mut error: *mut u8 = ptr::null_mut();
+                let mut error_size: size_t = 0;
+                assert_eq!(
+                    du_execute_ex(
+                        to_c_str!("sh").unwrap().as_ptr(),
+                        args.as_ptr(),
+                        ptr::null(),
+                        ptr::null(),
+                        &options,
+                        ptr::null(),
+                        0,
+                        200,
+                        0,
+                        DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                        ptr::null(),
+                        &mut output,
+                        &mut output_size,
+                        &mut error,
+                        &mut error_size,
+                        ptr::null_mut(),
+                        &mut status
+                    ),
+                    -4
+                );
+                let pid =
+                    String::from_utf8(std::slice::from_raw_parts(output, output_size).to_vec())
+                        .unwrap();
+                let pid = pid.trim();
+                du_dispose_bytes(output, output_size);
+                du_dispose_bytes(error, error_size);
+                assert!(if kill_tree { killed(pid) } else { alive(pid) });
+                libc::kill(pid.parse().unwrap(), libc::SIGKILL);
+            }
+            // The child exits on time, but the grandchild keeps the pipes open.
+            let detached_args: [*const c_char; 3] = [
+                args[0],
+                to_c_str!("sleep 30 & echo $!").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut error: *mut u8 = ptr::null_mut();
+            let mut error_size: size_t = 0;
             assert_eq!(
-                du_md5(to_c_str!("abc123").unwrap().as_ptr(), ptr::null_mut(), 33),
-                -1
+                du_execute_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    detached_args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &options,
+                    ptr::null(),
+                    0,
+                    200,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                -4
             );
+            let pid = String::from_utf8(std::slice::from_raw_parts(output, output_size).to_vec())
+                .unwrap();
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            assert!(killed(pid.trim()));
+            drop(CString::from_raw(detached_args[1] as *mut c_char));
+            let mut chunks: Vec<(DU_STREAM, Vec<u8>)> = Vec::new();
             assert_eq!(
-                du_md5(
-                    to_c_str!("abc123").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    0
+                du_execute_stream(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &options,
+                    ptr::null(),
+                    0,
+                    200,
+                    Some(collect),
+                    &mut chunks as *mut _ as *mut c_void,
+                    &mut status
                 ),
-                -1
+                -4
             );
+            let pid =
+                String::from_utf8(chunks.into_iter().flat_map(|(_, data)| data).collect()).unwrap();
+            assert!(killed(pid.trim()));
+            let stages = [DU_PIPELINE_STAGE {
+                program: to_c_str!("sh").unwrap().into_raw(),
+                args: args.as_ptr(),
+                envs: ptr::null(),
+                env_options: ptr::null(),
+                options: &options,
+            }];
+            let mut statuses = [DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            }];
             assert_eq!(
-                du_md5(
-                    to_c_str!("abc123").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    hash.len()
+                du_pipeline_execute(
+                    stages.as_ptr(),
+                    stages.len(),
+                    ptr::null(),
+                    0,
+                    200,
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    statuses.as_mut_ptr()
+                ),
+                -4
+            );
+            let pid = String::from_utf8(std::slice::from_raw_parts(output, output_size).to_vec())
+                .unwrap();
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            assert!(killed(pid.trim()));
+            drop(CString::from_raw(stages[0].program as *mut c_char));
+            let fifo = std::env::temp_dir().join("libduallutils-kill-tree.fifo");
+            fs::remove_file(&fifo).unwrap_or_default();
+            let fifo_c = CString::new(fifo.to_str().unwrap()).unwrap();
+            assert_eq!(libc::mkfifo(fifo_c.as_ptr(), 0o600), 0);
+            let script =
+                CString::new(format!("sleep 30 & echo $! > {}; wait", fifo.display())).unwrap();
+            let process_args: [*const c_char; 3] = [args[0], script.as_ptr(), ptr::null()];
+            let mut handle: *mut DU_PROCESS = ptr::null_mut();
+            let mut pid: c_uint = 0;
+            assert_eq!(
+                du_process_start_ex(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    process_args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &options,
+                    &mut handle,
+                    &mut pid
                 ),
                 0
             );
+            let grandchild = fs::read_to_string(&fifo).unwrap();
+            let grandchild = grandchild.trim();
+            assert!(alive(grandchild));
+            assert_eq!(du_process_kill(handle), 0);
+            assert!(killed(grandchild));
+            du_process_free(handle);
+            fs::remove_file(&fifo).unwrap();
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn process_kill_with_parent() {
+        unsafe {
+            let alive = |pid: c_uint| {
+                fs::read_to_string(format!("/proc/{}/stat", pid))
+                    .map(|stat| !stat.contains(") Z "))
+                    .unwrap_or(false)
+            };
+            for kill_with_parent in [false, true] {
+                // The child outlives the short-lived thread which spawned it.
+                let (handle, pid) = std::thread::spawn(move || {
+                    let options = DU_SPAWN_OPTIONS {
+                        kill_with_parent,
+                        ..spawn_options()
+                    };
+                    let args: [*const c_char; 2] =
+                        [to_c_str!("30").unwrap().into_raw(), ptr::null()];
+                    let mut handle: *mut DU_PROCESS = ptr::null_mut();
+                    let mut pid: c_uint = 0;
+                    assert_eq!(
+                        du_process_start_ex(
+                            to_c_str!("sleep").unwrap().as_ptr(),
+                            args.as_ptr(),
+                            ptr::null(),
+                            ptr::null(),
+                            &options,
+                            &mut handle,
+                            &mut pid
+                        ),
+                        0
+                    );
+                    drop(CString::from_raw(args[0] as *mut c_char));
+                    (handle as usize, pid)
+                })
+                .join()
+                .unwrap();
+                let handle = handle as *mut DU_PROCESS;
+                assert!(pid > 0);
+                let mut status = DU_EXIT_STATUS {
+                    kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                    code: -1,
+                };
+                assert_eq!(du_process_wait(handle, 200, &mut status), -4);
+                assert_eq!(du_process_kill(handle), 0);
+                du_process_free(handle);
+            }
+            // The child is killed when the process which spawned it exits.
+            let file = std::env::temp_dir().join("libduallutils-kill-with-parent.pid");
+            fs::remove_file(&file).unwrap_or_default();
+            let exited = Command::new(std::env::current_exe().unwrap())
+                .args([
+                    "--exact",
+                    "tests::process_kill_with_parent_helper",
+                    "--ignored",
+                    "--quiet",
+                ])
+                .env("DU_KILL_WITH_PARENT", &file)
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(exited.success());
+            let pid: c_uint = fs::read_to_string(&file).unwrap().parse().unwrap();
+            fs::remove_file(&file).unwrap();
+            let started = std::time::Instant::now();
+            while alive(pid) && started.elapsed().as_secs() < 5 {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            assert!(!alive(pid));
+        }
+    }
+
+    /// Run by `process_kill_with_parent` in a separate process, which exits leaving the child.
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore]
+    fn process_kill_with_parent_helper() {
+        let file = match std::env::var("DU_KILL_WITH_PARENT") {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        unsafe {
+            let options = DU_SPAWN_OPTIONS {
+                kill_with_parent: true,
+                ..spawn_options()
+            };
+            let args: [*const c_char; 2] = [to_c_str!("30").unwrap().into_raw(), ptr::null()];
+            let mut handle: *mut DU_PROCESS = ptr::null_mut();
+            let mut pid: c_uint = 0;
             assert_eq!(
-                from_c_str!(hash.as_ptr()).unwrap(),
-                "e99a18c428cb38d5f260853678922e03"
+                du_process_start_ex(
+                    to_c_str!("sleep").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &options,
+                    &mut handle,
+                    &mut pid
+                ),
+                0
             );
+            drop(CString::from_raw(args[0] as *mut c_char));
+            fs::write(file, pid.to_string()).unwrap();
         }
     }
 
     #[test]
-    fn md5_file() {
+    fn shell_execute() {
         unsafe {
-            let hash: [c_char; 33] = [0; 33];
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut error: *mut u8 = ptr::null_mut();
+            let mut error_size: size_t = 0;
+            let mut truncated = true;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
             assert_eq!(
-                du_md5_file(ptr::null(), hash.as_ptr() as *mut c_char, 33),
+                du_shell_execute(
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
+                ),
                 -1
             );
             assert_eq!(
-                du_md5_file(
-                    to_c_str!("abc123.txt").unwrap().as_ptr(),
+                du_shell_execute(
+                    to_c_str!("exit 0").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
                     ptr::null_mut(),
-                    33
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut()
                 ),
                 -1
             );
             assert_eq!(
-                du_md5_file(
-                    to_c_str!("abc123.txt").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    0
+                du_shell_execute(
+                    to_c_str!("echo foo | tr a-z A-Z; echo bar >&2; exit 3")
+                        .unwrap()
+                        .as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
                 ),
-                -1
+                0
             );
+            assert_eq!(status.code, 3);
+            assert_eq!(std::slice::from_raw_parts(output, output_size), b"FOO\n");
+            assert_eq!(std::slice::from_raw_parts(error, error_size), b"bar\n");
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            let mut quoted: *mut c_char = ptr::null_mut();
+            assert_eq!(du_shell_quote(ptr::null(), &mut quoted), -1);
+            let args: [*const c_char; 6] = [
+                CString::new("printf").unwrap().into_raw(),
+                CString::new("%s|").unwrap().into_raw(),
+                CString::new("it's a test").unwrap().into_raw(),
+                CString::new("$HOME; rm -rf *").unwrap().into_raw(),
+                CString::new("").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            assert_eq!(du_shell_quote(args.as_ptr(), &mut quoted), 0);
             assert_eq!(
-                du_md5_file(
-                    to_c_str!("blah blah").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    33
-                ),
-                -2
+                from_c_str!(quoted).unwrap(),
+                "printf '%s|' 'it'\\''s a test' '$HOME; rm -rf *' ''"
             );
             assert_eq!(
-                du_md5_file(
-                    to_c_str!("LICENSE").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    hash.len()
+                du_shell_execute(
+                    quoted,
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    DU_OVERFLOW::DU_OVERFLOW_DISCARD,
+                    ptr::null(),
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    &mut truncated,
+                    &mut status
                 ),
                 0
             );
             assert_eq!(
-                from_c_str!(hash.as_ptr()).unwrap(),
-                "d88e9e08385d2a17052dac348bde4bc1"
+                std::slice::from_raw_parts(output, output_size),
+                b"it's a test|$HOME; rm -rf *||"
             );
+            du_dispose(quoted);
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            for arg in &args[..5] {
+                drop(CString::from_raw(*arg as *mut c_char));
+            }
+            assert_eq!(shell::quote_windows("foo"), "foo");
+            assert_eq!(shell::quote_windows(r#"a "b" c\"#), r#"^"a \^"b\^" c\\^""#);
+            assert_eq!(shell::quote_windows("a&b|c"), "a^&b^|c");
         }
     }
 
+    #[cfg(target_os = "linux")]
     #[test]
-    fn sha1() {
+    fn pipeline_execute() {
         unsafe {
-            let hash: [c_char; 41] = [0; 41];
-            assert_eq!(du_sha1(ptr::null(), hash.as_ptr() as *mut c_char, 41), -1);
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut error: *mut u8 = ptr::null_mut();
+            let mut error_size: size_t = 0;
+            let mut statuses: Vec<DU_EXIT_STATUS> = (0..3)
+                .map(|_| DU_EXIT_STATUS {
+                    kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                    code: -1,
+                })
+                .collect();
+            let stage = |program: &CString, args: &[*const c_char]| DU_PIPELINE_STAGE {
+                program: program.as_ptr(),
+                args: args.as_ptr(),
+                envs: ptr::null(),
+                env_options: ptr::null(),
+                options: ptr::null(),
+            };
             assert_eq!(
-                du_sha1(to_c_str!("abc123").unwrap().as_ptr(), ptr::null_mut(), 41),
+                du_pipeline_execute(
+                    ptr::null(),
+                    0,
+                    ptr::null(),
+                    0,
+                    0,
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    statuses.as_mut_ptr()
+                ),
                 -1
             );
+            let sort = to_c_str!("sort").unwrap();
+            let sh = to_c_str!("sh").unwrap();
+            let tr = to_c_str!("tr").unwrap();
+            let none: [*const c_char; 1] = [ptr::null()];
+            let sh_args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("tr a-z A-Z; echo failure >&2; exit 2")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            let tr_args: [*const c_char; 3] = [
+                CString::new("-d").unwrap().into_raw(),
+                CString::new("B").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let stages = [
+                stage(&sort, &none),
+                stage(&sh, &sh_args),
+                stage(&tr, &tr_args),
+            ];
             assert_eq!(
-                du_sha1(
-                    to_c_str!("abc123").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    0
+                du_pipeline_execute(
+                    stages.as_ptr(),
+                    stages.len(),
+                    ptr::null(),
+                    0,
+                    0,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    statuses.as_mut_ptr()
                 ),
                 -1
             );
+            let input = "c\nb\na\n";
             assert_eq!(
-                du_sha1(
-                    to_c_str!("abc123").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    hash.len()
+                du_pipeline_execute(
+                    stages.as_ptr(),
+                    stages.len(),
+                    input.as_ptr(),
+                    input.len(),
+                    0,
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    statuses.as_mut_ptr()
                 ),
                 0
             );
+            assert_eq!(std::slice::from_raw_parts(output, output_size), b"A\n\nC\n");
+            assert_eq!(std::slice::from_raw_parts(error, error_size), b"failure\n");
             assert_eq!(
-                from_c_str!(hash.as_ptr()).unwrap(),
-                "6367c48dd193d56ea7b0baad25b19455e529f5ee"
+                statuses.iter().map(|s| s.code).collect::<Vec<c_int>>(),
+                [0, 2, 0]
+            );
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            let blah = to_c_str!("blah blah").unwrap();
+            let stages = [stage(&sort, &none), stage(&blah, &none)];
+            assert_eq!(
+                du_pipeline_execute(
+                    stages.as_ptr(),
+                    stages.len(),
+                    ptr::null(),
+                    0,
+                    0,
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    statuses.as_mut_ptr()
+                ),
+                -2
+            );
+            let sleep = to_c_str!("sleep").unwrap();
+            let sleep_args: [*const c_char; 2] =
+                [CString::new("5").unwrap().into_raw(), ptr::null()];
+            let stages = [stage(&sleep, &sleep_args), stage(&sort, &none)];
+            let started = std::time::Instant::now();
+            assert_eq!(
+                du_pipeline_execute(
+                    stages.as_ptr(),
+                    stages.len(),
+                    ptr::null(),
+                    0,
+                    200,
+                    &mut output,
+                    &mut output_size,
+                    &mut error,
+                    &mut error_size,
+                    statuses.as_mut_ptr()
+                ),
+                -4
             );
+            assert!(started.elapsed().as_secs() < 5);
+            du_dispose_bytes(output, output_size);
+            du_dispose_bytes(error, error_size);
+            for arg in sh_args[..2]
+                .iter()
+                .chain(&tr_args[..2])
+                .chain(&sleep_args[..1])
+            {
+                drop(CString::from_raw(*arg as *mut c_char));
+            }
         }
     }
 
+    #[cfg(target_os = "linux")]
     #[test]
-    fn sha1_file() {
+    fn execute_pty() {
         unsafe {
-            let hash: [c_char; 41] = [0; 41];
-            assert_eq!(
-                du_sha1_file(ptr::null(), hash.as_ptr() as *mut c_char, 41),
-                -1
-            );
+            let mut output: *mut u8 = ptr::null_mut();
+            let mut output_size: size_t = 0;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
             assert_eq!(
-                du_sha1_file(
-                    to_c_str!("abc123.txt").unwrap().as_ptr(),
-                    ptr::null_mut(),
-                    41
+                du_execute_pty(
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    &mut output,
+                    &mut output_size,
+                    &mut status
                 ),
                 -1
             );
             assert_eq!(
-                du_sha1_file(
-                    to_c_str!("abc123.txt").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    0
+                du_execute_pty(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    &mut output,
+                    &mut output_size,
+                    &mut status
                 ),
-                -1
+                -2
             );
             assert_eq!(
-                du_sha1_file(
+                du_execute_pty(
                     to_c_str!("blah blah").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    41
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    0,
+                    0,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut()
                 ),
-                -2
+                -1
             );
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new(
+                    "[ -t 0 ] && [ -t 1 ] && echo tty; stty size; read line; echo \"got $line\"",
+                )
+                .unwrap()
+                .into_raw(),
+                ptr::null(),
+            ];
+            let input = "hello\n";
             assert_eq!(
-                du_sha1_file(
-                    to_c_str!("LICENSE").unwrap().as_ptr(),
-                    hash.as_ptr() as *mut c_char,
-                    hash.len()
+                du_execute_pty(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    ptr::null(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    input.as_ptr(),
+                    input.len(),
+                    5000,
+                    30,
+                    100,
+                    &mut output,
+                    &mut output_size,
+                    &mut status
                 ),
                 0
             );
-            assert_eq!(
-                from_c_str!(hash.as_ptr()).unwrap(),
-                "6d842099530d126dea37db858a755e444f4de3f7"
-            );
+            assert_eq!(status.code, 0);
+            let terminal = String::from_utf8_lossy(std::slice::from_raw_parts(output, output_size));
+            assert!(terminal.contains("tty\r\n"));
+            assert!(terminal.contains("30 100\r\n"));
+            assert!(terminal.contains("got hello\r\n"));
+            du_dispose_bytes(output, output_size);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
         }
     }
 
+    #[cfg(target_os = "linux")]
+    extern "C" fn collect(
+        cls: *mut c_void,
+        stream: DU_STREAM,
+        data: *const u8,
+        size: size_t,
+    ) -> bool {
+        let chunks = unsafe { &mut *(cls as *mut Vec<(DU_STREAM, Vec<u8>)>) };
+        chunks.push((
+            stream,
+            unsafe { std::slice::from_raw_parts(data, size) }.to_vec(),
+        ));
+        true
+    }
+
+    #[cfg(target_os = "linux")]
+    extern "C" fn cancel(_: *mut c_void, _: DU_STREAM, _: *const u8, _: size_t) -> bool {
+        false
+    }
+
+    #[cfg(target_os = "linux")]
     #[test]
-    fn spawn() {
+    fn execute_stream() {
         unsafe {
-            let mut code: c_int = 0;
+            let mut chunks: Vec<(DU_STREAM, Vec<u8>)> = Vec::new();
+            let cls = &mut chunks as *mut _ as *mut c_void;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
             assert_eq!(
-                du_spawn(
+                du_execute_stream(
+                    to_c_str!("echo").unwrap().as_ptr(),
                     ptr::null(),
                     ptr::null(),
                     ptr::null(),
                     ptr::null(),
-                    true,
-                    &mut code
+                    ptr::null(),
+                    0,
+                    0,
+                    None,
+                    cls,
+                    &mut status
                 ),
                 -1
             );
             assert_eq!(
-                du_spawn(
+                du_execute_stream(
                     to_c_str!("blah blah").unwrap().as_ptr(),
                     ptr::null(),
                     ptr::null(),
                     ptr::null(),
-                    true,
-                    &mut code
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    Some(collect),
+                    cls,
+                    &mut status
                 ),
                 -2
             );
             assert_eq!(
-                du_spawn(
+                du_execute_stream(
                     to_c_str!("echo").unwrap().as_ptr(),
                     ptr::null(),
                     ptr::null(),
                     ptr::null(),
-                    true,
-                    &mut code
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    Some(collect),
+                    cls,
+                    ptr::null_mut()
+                ),
+                -1
+            );
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("echo out; echo err >&2; exit 1")
+                    .unwrap()
+                    .into_raw(),
+                ptr::null(),
+            ];
+            assert_eq!(
+                du_execute_stream(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    Some(collect),
+                    cls,
+                    &mut status
                 ),
                 0
             );
-            assert_eq!(code, 0);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            assert_eq!(status.kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert_eq!(status.code, 1);
+            let output = |stream| {
+                chunks
+                    .iter()
+                    .filter(|(s, _)| *s == stream)
+                    .flat_map(|(_, data)| data.clone())
+                    .collect::<Vec<u8>>()
+            };
+            assert_eq!(output(DU_STREAM::DU_STREAM_STDOUT), b"out\n");
+            assert_eq!(output(DU_STREAM::DU_STREAM_STDERR), b"err\n");
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("echo tick; sleep 5").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            let started = std::time::Instant::now();
+            assert_eq!(
+                du_execute_stream(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    ptr::null(),
+                    0,
+                    0,
+                    Some(cancel),
+                    ptr::null_mut(),
+                    &mut status
+                ),
+                -5
+            );
+            assert!(started.elapsed().as_secs() < 5);
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
         }
     }
 
+    #[cfg(target_os = "linux")]
     #[test]
-    fn execute() {
+    fn process() {
         unsafe {
-            let mut output: *mut c_char = ptr::null_mut();
-            let mut error: *mut c_char = ptr::null_mut();
-            let mut code: c_int = 0;
+            let mut handle: *mut DU_PROCESS = ptr::null_mut();
+            let mut pid: c_uint = 0;
+            let mut status = DU_EXIT_STATUS {
+                kind: DU_EXIT_KIND::DU_EXIT_CODE,
+                code: -1,
+            };
             assert_eq!(
-                du_execute(
+                du_process_start(
                     ptr::null(),
                     ptr::null(),
                     ptr::null(),
                     ptr::null(),
-                    &mut output,
-                    &mut error,
-                    &mut code
+                    ptr::null(),
+                    &mut handle,
+                    &mut pid
                 ),
                 -1
             );
             assert_eq!(
-                du_execute(
+                du_process_start(
                     to_c_str!("blah blah").unwrap().as_ptr(),
                     ptr::null(),
                     ptr::null(),
                     ptr::null(),
-                    &mut output,
-                    &mut error,
-                    &mut code
+                    ptr::null(),
+                    &mut handle,
+                    &mut pid
                 ),
                 -2
             );
-            let args: [*const c_char; 2] =
-                [CString::new("My test").unwrap().into_raw(), ptr::null()];
+            assert_eq!(du_process_wait(ptr::null_mut(), 0, &mut status), -1);
+            assert_eq!(du_process_is_running(ptr::null_mut()), false);
+            assert_eq!(du_process_kill(ptr::null_mut()), -1);
+            let args: [*const c_char; 2] = [CString::new("5").unwrap().into_raw(), ptr::null()];
             assert_eq!(
-                du_execute(
-                    to_c_str!("echo").unwrap().as_ptr(),
+                du_process_start(
+                    to_c_str!("sleep").unwrap().as_ptr(),
                     ptr::null(),
                     args.as_ptr(),
                     ptr::null(),
-                    &mut output,
-                    &mut error,
-                    &mut code
+                    ptr::null(),
+                    &mut handle,
+                    &mut pid
                 ),
                 0
             );
-            CString::from_raw(args[0] as *mut c_char);
-            assert_eq!(code, 0);
-            assert_eq!(from_c_str!(output).unwrap().trim(), "My test");
+            drop(CString::from_raw(args[0] as *mut c_char));
+            assert!(pid > 0);
+            assert_eq!(du_process_is_running(handle), true);
+            assert_eq!(du_process_wait(handle, 100, &mut status), -4);
+            assert_eq!(du_process_is_running(handle), true);
+            assert_eq!(du_process_kill(handle), 0);
+            assert_eq!(du_process_is_running(handle), false);
+            assert_eq!(du_process_wait(handle, 0, &mut status), 0);
+            assert_eq!(status.kind, DU_EXIT_KIND::DU_EXIT_SIGNAL);
+            assert_eq!(status.code, 9);
+            du_process_free(handle);
+            let args: [*const c_char; 3] = [
+                CString::new("-c").unwrap().into_raw(),
+                CString::new("exit 2").unwrap().into_raw(),
+                ptr::null(),
+            ];
+            assert_eq!(
+                du_process_start(
+                    to_c_str!("sh").unwrap().as_ptr(),
+                    ptr::null(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &mut handle,
+                    ptr::null_mut()
+                ),
+                0
+            );
+            drop(CString::from_raw(args[0] as *mut c_char));
+            drop(CString::from_raw(args[1] as *mut c_char));
+            assert_eq!(du_process_wait(handle, 5000, &mut status), 0);
+            assert_eq!(status.kind, DU_EXIT_KIND::DU_EXIT_CODE);
+            assert_eq!(status.code, 2);
+            assert_eq!(du_process_is_running(handle), false);
+            du_process_free(handle);
+            let args: [*const c_char; 2] = [CString::new("0.1").unwrap().into_raw(), ptr::null()];
+            let mut pid: c_uint = 0;
+            assert_eq!(
+                du_process_start(
+                    to_c_str!("sleep").unwrap().as_ptr(),
+                    ptr::null(),
+                    args.as_ptr(),
+                    ptr::null(),
+                    ptr::null(),
+                    &mut handle,
+                    &mut pid
+                ),
+                0
+            );
+            drop(CString::from_raw(args[0] as *mut c_char));
+            du_process_free(handle);
+            let proc = format!("/proc/{}", pid);
+            let started = std::time::Instant::now();
+            while Path::new(&proc).exists() && started.elapsed().as_secs() < 5 {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            assert!(!Path::new(&proc).exists());
         }
     }
 
@@ -959,7 +5906,6 @@ mod tests {
 
     #[test]
     fn deltree() {
-        use std::path::Path;
         unsafe {
             assert_eq!(du_deltree(ptr::null()), -1);
             fs::create_dir("tmp").unwrap_or_default();
@@ -981,4 +5927,395 @@ mod tests {
             assert_eq!(Path::new("tmp").exists(), false);
         }
     }
+
+    #[test]
+    fn deltree_secure() {
+        unsafe {
+            let mut files: u64 = 0;
+            let mut bytes: u64 = 0;
+            assert_eq!(
+                du_deltree_secure(ptr::null(), 1, &mut files, &mut bytes),
+                -1
+            );
+            assert_eq!(
+                du_deltree_secure(
+                    to_c_str!("tmp-secure").unwrap().as_ptr(),
+                    0,
+                    &mut files,
+                    &mut bytes
+                ),
+                -1
+            );
+            assert_eq!(
+                du_deltree_secure(to_c_str!("[").unwrap().as_ptr(), 1, &mut files, &mut bytes),
+                -1
+            );
+            assert_eq!(
+                du_deltree_secure(
+                    b"\xff\0".as_ptr() as *const c_char,
+                    1,
+                    &mut files,
+                    &mut bytes
+                ),
+                -1
+            );
+            assert_eq!(
+                du_deltree_secure(
+                    to_c_str!("tmp-secure").unwrap().as_ptr(),
+                    1,
+                    &mut files,
+                    &mut bytes
+                ),
+                -2
+            );
+            fs::create_dir("tmp-secure").unwrap_or_default();
+            fs::write(Path::new("tmp-secure").join("foo.txt"), "foo").unwrap();
+            fs::write(Path::new("tmp-secure").join("bar.txt"), "bar").unwrap();
+            fs::create_dir(Path::new("tmp-secure").join("foobar")).unwrap_or_default();
+            fs::write(
+                Path::new("tmp-secure").join("foobar").join("foobar.txt"),
+                "foobar",
+            )
+            .unwrap();
+            assert_eq!(
+                wipe_file(&Path::new("tmp-secure").join("foo.txt"), 2).unwrap(),
+                3
+            );
+            assert_eq!(
+                fs::read(Path::new("tmp-secure").join("foo.txt")).unwrap(),
+                [0xFF; 3]
+            );
+            assert_eq!(
+                du_deltree_secure(
+                    to_c_str!("tmp-secure/*.txt").unwrap().as_ptr(),
+                    3,
+                    &mut files,
+                    &mut bytes
+                ),
+                0
+            );
+            assert_eq!(files, 2);
+            assert_eq!(bytes, 6);
+            assert_eq!(Path::new("tmp-secure").join("foo.txt").exists(), false);
+            assert_eq!(Path::new("tmp-secure").join("bar.txt").exists(), false);
+            assert_eq!(
+                du_deltree_secure(
+                    to_c_str!("tmp-secure").unwrap().as_ptr(),
+                    1,
+                    ptr::null_mut(),
+                    &mut bytes
+                ),
+                0
+            );
+            assert_eq!(bytes, 6);
+            assert_eq!(Path::new("tmp-secure").exists(), false);
+        }
+    }
+
+    #[test]
+    fn certificate_info() {
+        unsafe {
+            let mut subject: *mut c_char = ptr::null_mut();
+            let mut issuer: *mut c_char = ptr::null_mut();
+            let mut serial: *mut c_char = ptr::null_mut();
+            let mut not_before: i64 = 0;
+            let mut not_after: i64 = 0;
+            let mut document: *mut c_char = ptr::null_mut();
+            assert_eq!(
+                du_certificate_info(
+                    ptr::null(),
+                    to_c_str!("123456").unwrap().as_ptr(),
+                    &mut subject,
+                    &mut issuer,
+                    &mut serial,
+                    &mut not_before,
+                    &mut not_after,
+                    &mut document
+                ),
+                -1
+            );
+            assert_eq!(
+                du_certificate_info(
+                    to_c_str!("blah blah").unwrap().as_ptr(),
+                    to_c_str!("123456").unwrap().as_ptr(),
+                    &mut subject,
+                    &mut issuer,
+                    &mut serial,
+                    &mut not_before,
+                    &mut not_after,
+                    &mut document
+                ),
+                -2
+            );
+            assert_eq!(
+                du_certificate_info(
+                    to_c_str!("tests/fixtures/a1.pfx").unwrap().as_ptr(),
+                    to_c_str!("654321").unwrap().as_ptr(),
+                    &mut subject,
+                    &mut issuer,
+                    &mut serial,
+                    &mut not_before,
+                    &mut not_after,
+                    &mut document
+                ),
+                -3
+            );
+            assert_eq!(
+                du_certificate_info(
+                    to_c_str!("LICENSE").unwrap().as_ptr(),
+                    to_c_str!("123456").unwrap().as_ptr(),
+                    &mut subject,
+                    &mut issuer,
+                    &mut serial,
+                    &mut not_before,
+                    &mut not_after,
+                    &mut document
+                ),
+                -4
+            );
+            assert_eq!(
+                du_certificate_info(
+                    to_c_str!("tests/fixtures/a1.pfx").unwrap().as_ptr(),
+                    to_c_str!("123456").unwrap().as_ptr(),
+                    &mut subject,
+                    &mut issuer,
+                    &mut serial,
+                    &mut not_before,
+                    &mut not_after,
+                    &mut document
+                ),
+                0
+            );
+            assert_eq!(
+                from_c_str!(subject).unwrap(),
+                "C=BR, O=ICP-Brasil, OU=Certificado PJ A1, CN=DUALL SISTEMAS LTDA:12345678000195"
+            );
+            assert_eq!(
+                from_c_str!(issuer).unwrap(),
+                "C=BR, O=ICP-Brasil, CN=AC DUALL TESTE"
+            );
+            assert_eq!(from_c_str!(serial).unwrap(), "1A2B3C4D");
+            assert_eq!(not_before, 1577836800);
+            assert_eq!(not_after, 2051222400);
+            assert_eq!(from_c_str!(document).unwrap(), "12345678000195");
+            du_dispose(subject);
+            du_dispose(issuer);
+            du_dispose(serial);
+            du_dispose(document);
+        }
+    }
+
+    #[cfg(feature = "legacy-provider")]
+    #[test]
+    fn certificate_info_legacy() {
+        unsafe {
+            let mut subject: *mut c_char = ptr::null_mut();
+            let mut issuer: *mut c_char = ptr::null_mut();
+            let mut serial: *mut c_char = ptr::null_mut();
+            let mut not_before: i64 = 0;
+            let mut not_after: i64 = 0;
+            let mut document: *mut c_char = ptr::null_mut();
+            assert_eq!(
+                du_certificate_info(
+                    to_c_str!("tests/fixtures/a1-legacy.pfx").unwrap().as_ptr(),
+                    to_c_str!("123456").unwrap().as_ptr(),
+                    &mut subject,
+                    &mut issuer,
+                    &mut serial,
+                    &mut not_before,
+                    &mut not_after,
+                    &mut document
+                ),
+                0
+            );
+            assert_eq!(from_c_str!(document).unwrap(), "12345678000195");
+            du_dispose(subject);
+            du_dispose(issuer);
+            du_dispose(serial);
+            du_dispose(document);
+        }
+    }
+
+    #[test]
+    fn xml_sign() {
+        unsafe {
+            let xml = to_c_str!(fs::read_to_string("tests/fixtures/nfe.xml").unwrap()).unwrap();
+            let filename = to_c_str!("tests/fixtures/a1.pfx").unwrap();
+            let password = to_c_str!("123456").unwrap();
+            let mut output: *mut c_char = ptr::null_mut();
+            assert_eq!(
+                du_xml_sign(
+                    ptr::null(),
+                    ptr::null(),
+                    filename.as_ptr(),
+                    password.as_ptr(),
+                    &mut output
+                ),
+                -1
+            );
+            assert_eq!(
+                du_xml_sign(
+                    xml.as_ptr(),
+                    ptr::null(),
+                    filename.as_ptr(),
+                    to_c_str!("654321").unwrap().as_ptr(),
+                    &mut output
+                ),
+                -3
+            );
+            assert_eq!(
+                du_xml_sign(
+                    to_c_str!("<NFe><infNFe>").unwrap().as_ptr(),
+                    ptr::null(),
+                    filename.as_ptr(),
+                    password.as_ptr(),
+                    &mut output
+                ),
+                -6
+            );
+            assert_eq!(
+                du_xml_sign(
+                    xml.as_ptr(),
+                    to_c_str!("infEvento").unwrap().as_ptr(),
+                    filename.as_ptr(),
+                    password.as_ptr(),
+                    &mut output
+                ),
+                -7
+            );
+            assert_eq!(
+                du_xml_sign(
+                    xml.as_ptr(),
+                    to_c_str!("infNFe").unwrap().as_ptr(),
+                    filename.as_ptr(),
+                    password.as_ptr(),
+                    &mut output
+                ),
+                0
+            );
+            let signed = from_c_str!(output).unwrap().to_string();
+            du_dispose(output);
+            assert!(signed
+                .contains("</infNFe><Signature xmlns=\"http://www.w3.org/2000/09/xmldsig#\">"));
+            assert!(signed
+                .contains("<Reference URI=\"#NFe35200112345678000195650010000000011000000010\">"));
+            assert!(signed.contains("</Signature>\n  </NFe>"));
+            assert_eq!(du_xml_verify(to_c_str!(signed).unwrap().as_ptr()), 0);
+            assert_eq!(
+                du_xml_sign(
+                    xml.as_ptr(),
+                    ptr::null(),
+                    filename.as_ptr(),
+                    password.as_ptr(),
+                    &mut output
+                ),
+                0
+            );
+            let signed = from_c_str!(output).unwrap().to_string();
+            du_dispose(output);
+            assert!(signed.contains("<Reference URI=\"\">"));
+            assert!(signed.contains("</Signature></enviNFe>"));
+            assert_eq!(du_xml_verify(to_c_str!(signed).unwrap().as_ptr()), 0);
+        }
+    }
+
+    #[test]
+    fn xml_verify() {
+        unsafe {
+            let xml = to_c_str!(fs::read_to_string("tests/fixtures/nfe.xml").unwrap()).unwrap();
+            let mut output: *mut c_char = ptr::null_mut();
+            assert_eq!(
+                du_xml_sign(
+                    xml.as_ptr(),
+                    to_c_str!("infNFe").unwrap().as_ptr(),
+                    to_c_str!("tests/fixtures/a1.pfx").unwrap().as_ptr(),
+                    to_c_str!("123456").unwrap().as_ptr(),
+                    &mut output
+                ),
+                0
+            );
+            let signed = from_c_str!(output).unwrap().to_string();
+            du_dispose(output);
+            assert_eq!(du_xml_verify(ptr::null()), -1);
+            assert_eq!(
+                du_xml_verify(to_c_str!("<NFe><infNFe>").unwrap().as_ptr()),
+                -2
+            );
+            assert_eq!(du_xml_verify(xml.as_ptr()), -3);
+            assert_eq!(
+                du_xml_verify(to_c_str!(signed.replace("12.50", "1.25")).unwrap().as_ptr()),
+                -4
+            );
+            let value = signed.find("<SignatureValue>").unwrap() + "<SignatureValue>".len();
+            let mut tampered = signed.clone();
+            tampered.replace_range(
+                value..value + 4,
+                if &signed[value..value + 4] == "AAAA" {
+                    "BBBB"
+                } else {
+                    "AAAA"
+                },
+            );
+            assert_eq!(du_xml_verify(to_c_str!(tampered).unwrap().as_ptr()), -5);
+            assert_eq!(
+                du_xml_verify(
+                    to_c_str!(signed.replace("#rsa-sha1", "#rsa-sha256"))
+                        .unwrap()
+                        .as_ptr()
+                ),
+                -6
+            );
+            assert_eq!(du_xml_verify(to_c_str!(signed).unwrap().as_ptr()), 0);
+        }
+    }
+
+    fn dsig_value(xml: &str, tag: &str) -> String {
+        let start = xml.find(&format!("<{}>", tag)).unwrap() + tag.len() + 2;
+        let end = start + xml[start..].find('<').unwrap();
+        xml[start..end].split_whitespace().collect()
+    }
+
+    #[test]
+    fn xml_verify_xmlsec() {
+        unsafe {
+            // Signed by xmlsec1 (libxmlsec1-openssl) with the key and certificate from a1.pfx.
+            let xmlsec = fs::read_to_string("tests/fixtures/nfe-xmlsec.xml").unwrap();
+            let doc = roxmltree::Document::parse(&xmlsec).unwrap();
+            let node = doc
+                .descendants()
+                .find(|n| n.tag_name().name() == "infNFe")
+                .unwrap();
+            let mut hasher = Sha1::new();
+            hasher.input_str(&xmldsig::canonicalize(node, None));
+            let mut digest = [0u8; 20];
+            hasher.result(&mut digest);
+            assert_eq!(BASE64.encode(&digest), dsig_value(&xmlsec, "DigestValue"));
+            assert_eq!(
+                du_xml_verify(to_c_str!(xmlsec.clone()).unwrap().as_ptr()),
+                0
+            );
+            let xml = to_c_str!(fs::read_to_string("tests/fixtures/nfe.xml").unwrap()).unwrap();
+            let mut output: *mut c_char = ptr::null_mut();
+            assert_eq!(
+                du_xml_sign(
+                    xml.as_ptr(),
+                    to_c_str!("infNFe").unwrap().as_ptr(),
+                    to_c_str!("tests/fixtures/a1.pfx").unwrap().as_ptr(),
+                    to_c_str!("123456").unwrap().as_ptr(),
+                    &mut output
+                ),
+                0
+            );
+            let signed = from_c_str!(output).unwrap().to_string();
+            du_dispose(output);
+            assert_eq!(
+                dsig_value(&signed, "DigestValue"),
+                dsig_value(&xmlsec, "DigestValue")
+            );
+            assert_eq!(
+                dsig_value(&signed, "SignatureValue"),
+                dsig_value(&xmlsec, "SignatureValue")
+            );
+        }
+    }
 }
@@ -1,8 +1,14 @@
-use libc::{c_char, c_int, mktime, settimeofday, time_t, timeval};
-use std::ffi::CStr;
-use std::io::{Error, ErrorKind};
+use crate::DU_SPAWN_OPTIONS;
+use libc::{c_char, c_int, c_uint, gid_t, mktime, settimeofday, time_t, timeval, uid_t};
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{self, Error, ErrorKind};
 use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
 use std::ptr;
+use std::sync::{mpsc, OnceLock};
 use sysinfo::Signal;
 use sysinfo::{ProcessExt, System, SystemExt};
 
@@ -45,20 +51,384 @@ pub unsafe fn datetime_set(
 pub unsafe fn terminate(process_name: *const c_char) -> c_int {
     let name = CStr::from_ptr(process_name).to_str().unwrap();
     let sys = System::new_all();
-    for proc in sys.get_process_by_name(name) {
-        match sys.get_process(proc.pid()) {
-            Some(p) => {
-                if p.kill(Signal::Term) {
-                    return 0;
+    if let Some(proc) = sys.get_process_by_name(name).first() {
+        if let Some(p) = sys.get_process(proc.pid()) {
+            if p.kill(Signal::Term) {
+                return 0;
+            }
+            if Error::last_os_error().kind() == ErrorKind::PermissionDenied {
+                return -3;
+            } else {
+                return -4;
+            }
+        }
+    }
+    -2
+}
+
+/// Marks all the file descriptors besides the standard ones as close-on-exec, so they are not
+/// inherited by the program about to be executed.
+unsafe fn close_on_exec() {
+    if libc::syscall(
+        libc::SYS_close_range,
+        3,
+        c_uint::MAX,
+        libc::CLOSE_RANGE_CLOEXEC,
+    ) == -1
+    {
+        let max = libc::sysconf(libc::_SC_OPEN_MAX);
+        let max = if max > 0 {
+            max.min(65536) as c_int
+        } else {
+            1024
+        };
+        for fd in 3..max {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            if flags != -1 {
+                libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+            }
+        }
+    }
+}
+
+/// Applies the spawn options to the command. `detached` runs the child in a new session, so it
+/// survives its parent, and `kill_tree` puts the child in its own process group so it can be
+/// killed with all of its descendants. `kill_with_parent` makes the kernel kill the child when the
+/// thread which spawned it exits, so it must be spawned by `spawn`. There are no console windows
+/// to hide or create, so `hidden` and `new_console` are not supported.
+pub fn spawn_options(cmd: &mut Command, options: &DU_SPAWN_OPTIONS) -> Result<(), c_int> {
+    if options.hidden || options.new_console || (options.detached && options.kill_with_parent) {
+        return Err(-1);
+    }
+    if options.kill_with_parent {
+        let parent = unsafe { libc::getpid() };
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) == -1 {
+                    return Err(Error::last_os_error());
                 }
-                if Error::last_os_error().kind() == ErrorKind::PermissionDenied {
-                    return -3;
-                } else {
-                    return -4;
+                // The parent may have died before the signal was set.
+                if libc::getppid() != parent {
+                    libc::raise(libc::SIGKILL);
                 }
+                Ok(())
+            });
+        }
+    }
+    if options.detached {
+        // The new session also makes the child lead a new process group.
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    } else if options.new_process_group || options.kill_tree {
+        cmd.process_group(0);
+    }
+    if !options.inherit_handles {
+        unsafe {
+            cmd.pre_exec(|| {
+                close_on_exec();
+                Ok(())
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Request to spawn a command from the spawner thread, whose caller waits for the reply, so the
+/// command outlives it.
+struct SpawnRequest(*mut Command, mpsc::Sender<io::Result<Child>>);
+
+unsafe impl Send for SpawnRequest {}
+
+/// Thread which spawns the children with `kill_with_parent`. The kernel sends their death signal
+/// when the thread which spawned them exits, so it never exits before the calling process.
+static SPAWNER: OnceLock<mpsc::Sender<SpawnRequest>> = OnceLock::new();
+
+/// Spawns the command, from the spawner thread if `kill_with_parent` is set, so the child is not
+/// killed when a short-lived calling thread exits.
+pub fn spawn(cmd: &mut Command, options: Option<&DU_SPAWN_OPTIONS>) -> io::Result<Child> {
+    if !options.is_some_and(|options| options.kill_with_parent) {
+        return cmd.spawn();
+    }
+    let spawner = SPAWNER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<SpawnRequest>();
+        std::thread::spawn(move || {
+            for SpawnRequest(cmd, reply) in rx {
+                reply.send(unsafe { (*cmd).spawn() }).unwrap_or_default();
             }
-            None => break,
+        });
+        tx
+    });
+    let (tx, rx) = mpsc::channel();
+    spawner
+        .send(SpawnRequest(cmd, tx))
+        .map_err(|_| Error::from(ErrorKind::BrokenPipe))?;
+    rx.recv().map_err(|_| Error::from(ErrorKind::BrokenPipe))?
+}
+
+/// Nothing is left to be applied to the running child, since all the spawn options are applied
+/// before it executes the program.
+pub fn spawned(_child: &mut Child, _options: &DU_SPAWN_OPTIONS) -> Result<(), c_int> {
+    Ok(())
+}
+
+/// Kills the process group of the process, which holds all of its descendants that did not move
+/// to another group or session. The group ID is the process ID, since `spawn_options` makes the
+/// process lead a new group when `kill_tree` is set, so the group is still found after the process
+/// itself was reaped.
+pub fn kill_tree(pid: c_uint) {
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+/// Entry of the user database, where `name` and `gid` are unknown for a numeric user without an
+/// entry.
+struct Passwd {
+    name: Option<CString>,
+    uid: uid_t,
+    gid: Option<gid_t>,
+}
+
+unsafe fn passwd(user: &str) -> Option<Passwd> {
+    let name = CString::new(user).ok()?;
+    let id = user.parse::<uid_t>().ok();
+    let mut pwd: libc::passwd = mem::zeroed();
+    let mut result: *mut libc::passwd = ptr::null_mut();
+    let mut buf: Vec<c_char> = vec![0; 16384];
+    loop {
+        let code = match id {
+            Some(uid) => libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result),
+            None => libc::getpwnam_r(
+                name.as_ptr(),
+                &mut pwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            ),
         };
+        if code == libc::ERANGE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if result.is_null() {
+            return id.map(|uid| Passwd {
+                name: None,
+                uid,
+                gid: None,
+            });
+        }
+        return Some(Passwd {
+            name: Some(CStr::from_ptr(pwd.pw_name).to_owned()),
+            uid: pwd.pw_uid,
+            gid: Some(pwd.pw_gid),
+        });
+    }
+}
+
+unsafe fn group(group: &str) -> Option<gid_t> {
+    if let Ok(gid) = group.parse::<gid_t>() {
+        return Some(gid);
+    }
+    let name = CString::new(group).ok()?;
+    let mut grp: libc::group = mem::zeroed();
+    let mut result: *mut libc::group = ptr::null_mut();
+    let mut buf: Vec<c_char> = vec![0; 16384];
+    loop {
+        let code = libc::getgrnam_r(
+            name.as_ptr(),
+            &mut grp,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        );
+        if code == libc::ERANGE {
+            buf.resize(buf.len() * 2, 0);
+            continue;
+        }
+        if result.is_null() {
+            return None;
+        }
+        return Some(grp.gr_gid);
+    }
+}
+
+unsafe fn grouplist(name: &CStr, gid: gid_t) -> Vec<gid_t> {
+    let mut count: c_int = 32;
+    loop {
+        let mut groups: Vec<gid_t> = vec![0; count as usize];
+        let capacity = count;
+        if libc::getgrouplist(name.as_ptr(), gid, groups.as_mut_ptr(), &mut count) != -1 {
+            groups.truncate(count as usize);
+            return groups;
+        }
+        count = count.max(capacity * 2);
+    }
+}
+
+/// Makes the child run as the user and groups, given by name or numeric ID.
+///
+/// When the user is given, the group defaults to its primary group and the supplementary groups
+/// default to the ones it is a member of.
+///
+/// Returns `-6` if some user or group is not found.
+pub unsafe fn credentials(
+    cmd: &mut Command,
+    user: Option<&str>,
+    group: Option<&str>,
+    groups: Option<Vec<&str>>,
+) -> Result<(), c_int> {
+    let passwd = match user {
+        Some(user) => Some(passwd(user).ok_or(-6)?),
+        None => None,
+    };
+    let gid = match group {
+        Some(group) => Some(self::group(group).ok_or(-6)?),
+        None => passwd.as_ref().and_then(|passwd| passwd.gid),
+    };
+    if passwd.is_some() && gid.is_none() {
+        return Err(-6);
+    }
+    let groups = match groups {
+        Some(groups) => Some(
+            groups
+                .into_iter()
+                .map(|name| self::group(name))
+                .collect::<Option<Vec<gid_t>>>()
+                .ok_or(-6)?,
+        ),
+        None => match (&passwd, gid) {
+            (
+                Some(Passwd {
+                    name: Some(name), ..
+                }),
+                Some(gid),
+            ) => Some(grouplist(name, gid)),
+            (Some(_), Some(gid)) => Some(vec![gid]),
+            _ => None,
+        },
+    };
+    let uid = passwd.map(|passwd| passwd.uid);
+    // The IDs are set here instead of by `Command::uid()` and `Command::gid()`, since those would
+    // drop the supplementary groups set before them.
+    cmd.pre_exec(move || {
+        // The kernel clears the parent death signal when the credentials change.
+        let mut signal: c_int = 0;
+        libc::prctl(libc::PR_GET_PDEATHSIG, &mut signal);
+        if let Some(groups) = &groups {
+            if libc::setgroups(groups.len(), groups.as_ptr()) == -1 {
+                return Err(Error::last_os_error());
+            }
+        }
+        if let Some(gid) = gid {
+            if libc::setgid(gid) == -1 {
+                return Err(Error::last_os_error());
+            }
+        }
+        if let Some(uid) = uid {
+            if libc::setuid(uid) == -1 {
+                return Err(Error::last_os_error());
+            }
+        }
+        if signal != 0 {
+            libc::prctl(libc::PR_SET_PDEATHSIG, signal);
+        }
+        Ok(())
+    });
+    Ok(())
+}
+
+/// Applies the resource limits, nice value and umask to the child, where `0` keeps the limit or
+/// nice value and a negative umask keeps the umask.
+pub fn limits(
+    cmd: &mut Command,
+    address_space: u64,
+    cpu_time: u64,
+    open_files: u64,
+    nice: c_int,
+    umask: c_int,
+) -> Result<(), c_int> {
+    let limits = [
+        (libc::RLIMIT_AS, address_space),
+        (libc::RLIMIT_CPU, cpu_time),
+        (libc::RLIMIT_NOFILE, open_files),
+    ];
+    unsafe {
+        cmd.pre_exec(move || {
+            for (resource, value) in limits.iter() {
+                if *value > 0 {
+                    let limit = libc::rlimit {
+                        rlim_cur: *value as libc::rlim_t,
+                        rlim_max: *value as libc::rlim_t,
+                    };
+                    if libc::setrlimit(*resource, &limit) == -1 {
+                        return Err(Error::last_os_error());
+                    }
+                }
+            }
+            if (-20..=19).contains(&nice) && libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                return Err(Error::last_os_error());
+            }
+            if umask >= 0 {
+                libc::umask(umask as libc::mode_t);
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// Attaches the child to a new pseudo-terminal with the given size, as its controlling terminal
+/// and standard streams.
+///
+/// Returns the master side of the pseudo-terminal.
+pub fn pty(cmd: &mut Command, rows: u16, cols: u16) -> Result<File, Error> {
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC);
+        if master == -1 {
+            return Err(Error::last_os_error());
+        }
+        let master = File::from_raw_fd(master);
+        let fd = master.as_raw_fd();
+        if libc::grantpt(fd) == -1 || libc::unlockpt(fd) == -1 {
+            return Err(Error::last_os_error());
+        }
+        let mut name: Vec<c_char> = vec![0; 128];
+        let ret = libc::ptsname_r(fd, name.as_mut_ptr(), name.len());
+        if ret != 0 {
+            return Err(Error::from_raw_os_error(ret));
+        }
+        let size = libc::winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        if libc::ioctl(fd, libc::TIOCSWINSZ, &size) == -1 {
+            return Err(Error::last_os_error());
+        }
+        let slave = libc::open(
+            name.as_ptr(),
+            libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+        );
+        if slave == -1 {
+            return Err(Error::last_os_error());
+        }
+        let slave = File::from_raw_fd(slave);
+        cmd.stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        cmd.pre_exec(|| {
+            if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        });
+        Ok(master)
     }
-    -2
 }
@@ -0,0 +1,102 @@
+use crate::DU_OTP_ALGORITHM::{self, DU_OTP_SHA1, DU_OTP_SHA256};
+use crypto::digest::Digest;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha1::Sha1;
+use crypto::sha2::Sha256;
+use crypto::util::fixed_time_eq;
+use data_encoding::BASE32_NOPAD;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn hmac<D: Digest>(digest: D, secret: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::new(digest, secret);
+    mac.input(message);
+    mac.result().code().to_vec()
+}
+
+pub fn hotp(secret: &[u8], counter: u64, digits: u32, algorithm: &DU_OTP_ALGORITHM) -> String {
+    let message = counter.to_be_bytes();
+    let hash = match algorithm {
+        DU_OTP_SHA1 => hmac(Sha1::new(), secret, &message),
+        DU_OTP_SHA256 => hmac(Sha256::new(), secret, &message),
+    };
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = (u32::from(hash[offset]) & 0x7f) << 24
+        | u32::from(hash[offset + 1]) << 16
+        | u32::from(hash[offset + 2]) << 8
+        | u32::from(hash[offset + 3]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(digits),
+        width = digits as usize
+    )
+}
+
+pub fn hotp_verify(
+    secret: &[u8],
+    counter: u64,
+    digits: u32,
+    algorithm: &DU_OTP_ALGORITHM,
+    code: &str,
+) -> bool {
+    fixed_time_eq(
+        hotp(secret, counter, digits, algorithm).as_bytes(),
+        code.as_bytes(),
+    )
+}
+
+pub fn timestamp_or_now(timestamp: u64) -> u64 {
+    if timestamp > 0 {
+        return timestamp;
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn uri(
+    kind: &str,
+    secret: &[u8],
+    account: &str,
+    issuer: Option<&str>,
+    digits: u32,
+    algorithm: &DU_OTP_ALGORITHM,
+    parameter: (&str, u64),
+) -> String {
+    let label = match issuer {
+        Some(issuer) => format!("{}:{}", percent_encode(issuer), percent_encode(account)),
+        None => percent_encode(account),
+    };
+    let mut uri = format!(
+        "otpauth://{}/{}?secret={}",
+        kind,
+        label,
+        BASE32_NOPAD.encode(secret)
+    );
+    if let Some(issuer) = issuer {
+        uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+    }
+    let algorithm = match algorithm {
+        DU_OTP_SHA1 => "SHA1",
+        DU_OTP_SHA256 => "SHA256",
+    };
+    uri.push_str(&format!(
+        "&algorithm={}&digits={}&{}={}",
+        algorithm, digits, parameter.0, parameter.1
+    ));
+    uri
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,614 @@
+use crate::os;
+use crate::DU_EXIT_KIND::{DU_EXIT_CODE, DU_EXIT_CORE_DUMP, DU_EXIT_SIGNAL};
+use crate::DU_REDIRECT_MODE::{
+    DU_REDIRECT_APPEND, DU_REDIRECT_INHERIT, DU_REDIRECT_NULL, DU_REDIRECT_STDOUT,
+    DU_REDIRECT_TRUNCATE,
+};
+use crate::DU_STREAM::{self, DU_STREAM_STDERR, DU_STREAM_STDOUT};
+use crate::{from_c_array, from_c_str};
+use crate::{DU_ENV_OPTIONS, DU_EXIT_STATUS, DU_REDIRECT, DU_SPAWN_OPTIONS};
+use libc::{c_char, c_int, c_uint};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Creates the command from the C-like arguments shared by the spawn/execute functions.
+pub unsafe fn command(
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+) -> Command {
+    let mut cmd = Command::new(from_c_str!(program).unwrap());
+    if !args.is_null() {
+        cmd.args(&from_c_array!(args));
+    }
+    setup(&mut cmd, workdir, envs, env_options);
+    cmd
+}
+
+/// Creates the command running the program through an elevation helper, i.e. `helper [options]
+/// program [args]`.
+pub unsafe fn elevated(
+    helper: &str,
+    options: &[&str],
+    program: *const c_char,
+    workdir: *const c_char,
+    args: *const *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+) -> Command {
+    let mut cmd = Command::new(helper);
+    cmd.args(options).arg(from_c_str!(program).unwrap());
+    if !args.is_null() {
+        cmd.args(&from_c_array!(args));
+    }
+    setup(&mut cmd, workdir, envs, env_options);
+    cmd
+}
+
+/// Creates the command running the command line through the system shell, i.e. `/bin/sh -c` or
+/// `cmd /C` on Windows.
+pub unsafe fn shell(
+    command: *const c_char,
+    workdir: *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+) -> Command {
+    let command = from_c_str!(command).unwrap();
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = {
+        let mut cmd = Command::new("/bin/sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        use std::os::windows::process::CommandExt;
+        // cmd.exe does its own parsing of the command line, so it must not be quoted again.
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").raw_arg(command);
+        cmd
+    };
+    setup(&mut cmd, workdir, envs, env_options);
+    cmd
+}
+
+/// Applies the working directory and environment to the command.
+///
+/// The environment options are applied before the `envs`, so the latter are always passed to
+/// the child.
+unsafe fn setup(
+    cmd: &mut Command,
+    workdir: *const c_char,
+    envs: *const *const c_char,
+    env_options: *const DU_ENV_OPTIONS,
+) {
+    if !workdir.is_null() {
+        cmd.current_dir(from_c_str!(workdir).unwrap());
+    }
+    if !env_options.is_null() {
+        let options = &*env_options;
+        if options.clear {
+            cmd.env_clear();
+            if !options.inherit.is_null() {
+                for name in from_c_array!(options.inherit) {
+                    if let Some(value) = env::var_os(name) {
+                        cmd.env(name, value);
+                    }
+                }
+            }
+        }
+        if !options.remove.is_null() {
+            for name in from_c_array!(options.remove) {
+                cmd.env_remove(name);
+            }
+        }
+    }
+    if !envs.is_null() {
+        for env in from_c_array!(envs) {
+            let pair: Vec<&str> = env.splitn(2, '=').collect();
+            if pair.len() == 2 {
+                cmd.env(pair[0], pair[1]);
+            }
+        }
+    }
+}
+
+enum Target {
+    Inherit,
+    Null,
+    File(File),
+}
+
+impl Target {
+    unsafe fn open(redirect: *const DU_REDIRECT) -> Result<Target, c_int> {
+        if redirect.is_null() {
+            return Ok(Target::Inherit);
+        }
+        let redirect = &*redirect;
+        let mut options = OpenOptions::new();
+        match redirect.mode {
+            DU_REDIRECT_INHERIT => return Ok(Target::Inherit),
+            DU_REDIRECT_NULL => return Ok(Target::Null),
+            DU_REDIRECT_TRUNCATE => options.write(true).create(true).truncate(true),
+            DU_REDIRECT_APPEND => options.append(true).create(true),
+            DU_REDIRECT_STDOUT => return Err(-1),
+        };
+        if redirect.path.is_null() {
+            return Err(-1);
+        }
+        let path = from_c_str!(redirect.path).map_err(|_| -1)?;
+        options.open(path).map(Target::File).map_err(|_| -5)
+    }
+
+    /// Creates the child's stream, where `merged` makes an inherited stream go to the parent's
+    /// `stdout`.
+    fn stdio(&self, merged: bool) -> io::Result<Stdio> {
+        Ok(match self {
+            Target::Inherit if merged => Stdio::from(io::stdout()),
+            Target::Inherit => Stdio::inherit(),
+            Target::Null => Stdio::null(),
+            Target::File(file) => Stdio::from(file.try_clone()?),
+        })
+    }
+}
+
+/// Redirects the child's `stdout` and `stderr`, where a null redirection means inheriting the
+/// parent's stream.
+///
+/// Returns `-1` for an invalid redirection, `-3` for an unknown error, or `-5` if the file of a
+/// redirection cannot be opened.
+pub unsafe fn redirect(
+    cmd: &mut Command,
+    stdout: *const DU_REDIRECT,
+    stderr: *const DU_REDIRECT,
+) -> Result<(), c_int> {
+    let out = Target::open(stdout)?;
+    let err = if !stderr.is_null() && matches!((*stderr).mode, DU_REDIRECT_STDOUT) {
+        out.stdio(true)
+    } else {
+        Target::open(stderr)?.stdio(false)
+    };
+    cmd.stdout(out.stdio(false).map_err(|_| -3)?);
+    cmd.stderr(err.map_err(|_| -3)?);
+    Ok(())
+}
+
+/// Converts a timeout in milliseconds to a deadline, where `0` means no timeout.
+pub fn deadline(timeout: c_uint) -> Option<Instant> {
+    if timeout == 0 {
+        None
+    } else {
+        Some(Instant::now() + Duration::from_millis(u64::from(timeout)))
+    }
+}
+
+/// Waits for the child to exit until the deadline, killing it (and all of its descendants if
+/// `tree` is set) when the deadline expires.
+///
+/// Returns `None` if the child was killed due to the timeout.
+pub fn wait(
+    child: &mut Child,
+    deadline: Option<Instant>,
+    tree: bool,
+) -> io::Result<Option<ExitStatus>> {
+    let status = poll(child, deadline)?;
+    if status.is_none() {
+        kill(child, tree).unwrap_or_default();
+    }
+    Ok(status)
+}
+
+/// Waits for the child to exit until the deadline, leaving it running when the deadline expires.
+///
+/// Returns `None` if the child is still running.
+pub fn poll(child: &mut Child, deadline: Option<Instant>) -> io::Result<Option<ExitStatus>> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return child.wait().map(Some),
+    };
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Converts the exit status to its C representation, telling a normal exit from a termination
+/// by a signal.
+pub fn exit_status(status: ExitStatus) -> DU_EXIT_STATUS {
+    if let Some(code) = status.code() {
+        return DU_EXIT_STATUS {
+            kind: DU_EXIT_CODE,
+            code,
+        };
+    }
+    #[cfg(unix)]
+    if let Some(signal) = status.signal() {
+        return DU_EXIT_STATUS {
+            kind: if status.core_dumped() {
+                DU_EXIT_CORE_DUMP
+            } else {
+                DU_EXIT_SIGNAL
+            },
+            code: signal,
+        };
+    }
+    DU_EXIT_STATUS {
+        kind: DU_EXIT_SIGNAL,
+        code: 0,
+    }
+}
+
+/// Kills the child (if it is still running) and reaps it, where `tree` makes all of its
+/// descendants be killed as well. The child is not reaped if it cannot be killed.
+pub fn kill(child: &mut Child, tree: bool) -> io::Result<()> {
+    if tree {
+        os::kill_tree(child.id());
+    }
+    child.kill()?;
+    child.wait().map(|_| ())
+}
+
+/// Collects everything read from a child's pipe in a background thread, so the data read so far
+/// is still available if the child is killed while some descendant keeps the pipe open.
+///
+/// Data beyond the limit is discarded, raising the `overflow` flag, while the pipe keeps being
+/// drained so the child is not blocked.
+pub struct Capture {
+    data: Arc<Mutex<Vec<u8>>>,
+    done: Receiver<()>,
+}
+
+impl Capture {
+    pub fn new<R: Read + Send + 'static>(
+        pipe: Option<R>,
+        limit: usize,
+        overflow: Arc<AtomicBool>,
+    ) -> Self {
+        let data = Arc::new(Mutex::new(Vec::new()));
+        let (tx, done) = mpsc::channel();
+        if let Some(mut pipe) = pipe {
+            let data = data.clone();
+            thread::spawn(move || {
+                let mut buf = [0u8; crate::BUFFER_SIZE];
+                loop {
+                    match pipe.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            let mut data = data.lock().unwrap();
+                            let room = limit - data.len();
+                            if n > room {
+                                overflow.store(true, Ordering::SeqCst);
+                            }
+                            data.extend_from_slice(&buf[..n.min(room)]);
+                        }
+                        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(_) => break,
+                    }
+                }
+                tx.send(()).unwrap_or_default();
+            });
+        }
+        Capture { data, done }
+    }
+
+    /// Waits for the end of the stream until the deadline.
+    ///
+    /// Returns `false` if the deadline expired before the end of the stream.
+    pub fn join(&self, deadline: Option<Instant>) -> bool {
+        match deadline {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                !matches!(
+                    self.done.recv_timeout(timeout),
+                    Err(RecvTimeoutError::Timeout)
+                )
+            }
+            None => {
+                self.done.recv().unwrap_or_default();
+                true
+            }
+        }
+    }
+
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.data.lock().unwrap())
+    }
+}
+
+/// Writes the input to the child's `stdin` in a background thread, closing it at the end, so the
+/// child can produce output while it is still being fed.
+pub fn feed(child: &mut Child, input: Option<Vec<u8>>) {
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        thread::spawn(move || {
+            stdin.write_all(&input).unwrap_or_default();
+        });
+    }
+}
+
+/// Output captured from a child.
+pub struct Output {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Exit status, which is `None` if the child was killed due to the timeout.
+    pub status: Option<ExitStatus>,
+    /// Whether some output was discarded for exceeding the limit.
+    pub truncated: bool,
+}
+
+/// Waits for the child and its output until the deadline, capturing up to `limit` bytes of each
+/// stream, where `kill` makes the child be killed as soon as a stream exceeds the limit and `tree`
+/// makes its descendants be killed along with it.
+pub fn output(
+    mut child: Child,
+    input: Option<Vec<u8>>,
+    deadline: Option<Instant>,
+    limit: usize,
+    kill: bool,
+    tree: bool,
+) -> io::Result<Output> {
+    feed(&mut child, input);
+    let overflow = Arc::new(AtomicBool::new(false));
+    let stdout = Capture::new(child.stdout.take(), limit, overflow.clone());
+    let stderr = Capture::new(child.stderr.take(), limit, overflow.clone());
+    let mut status = if kill {
+        watch(&mut child, deadline, &overflow, tree)?
+    } else {
+        wait(&mut child, deadline, tree)?
+    };
+    let killed = status.is_none() || (kill && overflow.load(Ordering::SeqCst));
+    if killed || !(stdout.join(deadline) && stderr.join(deadline)) {
+        if !killed {
+            // Some descendant keeps the pipes open after the child exited.
+            if tree {
+                os::kill_tree(child.id());
+            }
+            status = None;
+        }
+        // Gives the readers a chance to consume what is still buffered in the pipes.
+        let grace = Some(Instant::now() + POLL_INTERVAL * 10);
+        stdout.join(grace);
+        stderr.join(grace);
+    }
+    Ok(Output {
+        stdout: stdout.take(),
+        stderr: stderr.take(),
+        status,
+        truncated: overflow.load(Ordering::SeqCst),
+    })
+}
+
+/// Waits for a child attached to a pseudo-terminal and its output until the deadline, writing the
+/// input to the terminal.
+///
+/// Returns the terminal output and the exit status, which is `None` if the child was killed due
+/// to the timeout.
+pub fn terminal(
+    mut child: Child,
+    master: File,
+    input: Option<Vec<u8>>,
+    deadline: Option<Instant>,
+) -> io::Result<(Vec<u8>, Option<ExitStatus>)> {
+    if let Some(input) = input {
+        let mut writer = master.try_clone()?;
+        thread::spawn(move || {
+            writer.write_all(&input).unwrap_or_default();
+        });
+    }
+    // Reading the master side fails with `EIO` once every process holding the terminal exits.
+    let capture = Capture::new(Some(master), usize::MAX, Arc::new(AtomicBool::new(false)));
+    let mut status = wait(&mut child, deadline, false)?;
+    let until = match status {
+        Some(_) => deadline,
+        None => Some(Instant::now() + POLL_INTERVAL * 10),
+    };
+    if !capture.join(until) {
+        status = None;
+    }
+    Ok((capture.take(), status))
+}
+
+/// Waits for the child to exit until the deadline, killing it (and all of its descendants if
+/// `tree` is set) when the deadline expires or the `overflow` flag is raised.
+///
+/// Returns `None` if the child was killed due to the timeout.
+fn watch(
+    child: &mut Child,
+    deadline: Option<Instant>,
+    overflow: &AtomicBool,
+    tree: bool,
+) -> io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if overflow.load(Ordering::SeqCst) {
+            kill(child, tree).unwrap_or_default();
+            return child.try_wait();
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            kill(child, tree).unwrap_or_default();
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Spawns the commands connecting the `stdout` of each one to the `stdin` of the next, killing
+/// the ones already spawned if some of them fails, whose index is returned along with the error.
+///
+/// Each command is paired with its spawn options, and each child with whether its descendants
+/// are killed along with it.
+pub fn spawn_pipeline(
+    commands: Vec<(Command, Option<&DU_SPAWN_OPTIONS>)>,
+    input: bool,
+) -> Result<Vec<(Child, bool)>, (usize, io::Error)> {
+    let mut children: Vec<(Child, bool)> = Vec::with_capacity(commands.len());
+    for (mut cmd, options) in commands {
+        let stdin = match children.last_mut() {
+            Some((previous, _)) => Stdio::from(previous.stdout.take().unwrap()),
+            None if input => Stdio::piped(),
+            None => Stdio::null(),
+        };
+        cmd.stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let tree = options.is_some_and(|options| options.kill_tree);
+        match os::spawn(&mut cmd, options) {
+            Ok(child) => children.push((child, tree)),
+            Err(error) => {
+                let index = children.len();
+                for (child, tree) in children.iter_mut() {
+                    kill(child, *tree).unwrap_or_default();
+                }
+                return Err((index, error));
+            }
+        }
+    }
+    Ok(children)
+}
+
+/// Output captured from a pipeline.
+pub struct PipelineOutput {
+    /// Data written by the last child to `stdout`.
+    pub stdout: Vec<u8>,
+    /// Data written by all the children to `stderr`, in the order of the pipeline.
+    pub stderr: Vec<u8>,
+    /// Exit status of each child, which is `None` if the pipeline was killed due to the timeout.
+    pub statuses: Option<Vec<ExitStatus>>,
+}
+
+/// Waits for all the children of a pipeline and its output until the deadline, killing all of
+/// them (and the descendants of those paired with `true`) when the deadline expires.
+pub fn pipeline(
+    mut children: Vec<(Child, bool)>,
+    input: Option<Vec<u8>>,
+    deadline: Option<Instant>,
+) -> io::Result<PipelineOutput> {
+    feed(&mut children[0].0, input);
+    let overflow = Arc::new(AtomicBool::new(false));
+    let last = children.len() - 1;
+    let stdout = Capture::new(children[last].0.stdout.take(), usize::MAX, overflow.clone());
+    let stderrs: Vec<Capture> = children
+        .iter_mut()
+        .map(|(child, _)| Capture::new(child.stderr.take(), usize::MAX, overflow.clone()))
+        .collect();
+    let mut statuses = Vec::with_capacity(children.len());
+    for (child, tree) in children.iter_mut() {
+        match wait(child, deadline, *tree)? {
+            Some(status) => statuses.push(status),
+            None => break,
+        }
+    }
+    let mut timed_out = statuses.len() < children.len();
+    if timed_out {
+        for (child, tree) in children.iter_mut() {
+            kill(child, *tree).unwrap_or_default();
+        }
+    }
+    let until = if timed_out {
+        // Gives the readers a chance to consume what is still buffered in the pipes.
+        Some(Instant::now() + POLL_INTERVAL * 10)
+    } else {
+        deadline
+    };
+    for capture in std::iter::once(&stdout).chain(stderrs.iter()) {
+        if !capture.join(until) {
+            timed_out = true;
+        }
+    }
+    Ok(PipelineOutput {
+        stdout: stdout.take(),
+        stderr: stderrs.iter().flat_map(|capture| capture.take()).collect(),
+        statuses: if timed_out { None } else { Some(statuses) },
+    })
+}
+
+/// How a streamed child finished.
+pub enum Termination {
+    Exited(ExitStatus),
+    TimedOut,
+    Cancelled,
+}
+
+fn forward<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    stream: DU_STREAM,
+    tx: Sender<(DU_STREAM, Vec<u8>)>,
+) {
+    if let Some(mut pipe) = pipe {
+        thread::spawn(move || {
+            let mut buf = [0u8; crate::BUFFER_SIZE];
+            loop {
+                match pipe.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        if tx.send((stream, buf[..n].to_vec())).is_err() {
+                            break;
+                        }
+                    }
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(_) => break,
+                }
+            }
+        });
+    }
+}
+
+/// Passes each chunk read from the child's `stdout` and `stderr` to the callback as soon as it
+/// arrives, until the child exits, the deadline expires or the callback returns `false`.
+///
+/// The callback is always called from the current thread, and the child is killed if it is
+/// timed out or cancelled, where `tree` makes all of its descendants be killed as well.
+pub fn stream<F: FnMut(DU_STREAM, &[u8]) -> bool>(
+    mut child: Child,
+    input: Option<Vec<u8>>,
+    deadline: Option<Instant>,
+    tree: bool,
+    mut callback: F,
+) -> io::Result<Termination> {
+    feed(&mut child, input);
+    let (tx, rx) = mpsc::channel();
+    forward(child.stdout.take(), DU_STREAM_STDOUT, tx.clone());
+    forward(child.stderr.take(), DU_STREAM_STDERR, tx);
+    loop {
+        let chunk = match deadline {
+            Some(deadline) => rx.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match chunk {
+            Ok((stream, data)) => {
+                if !callback(stream, &data) {
+                    kill(&mut child, tree).unwrap_or_default();
+                    return Ok(Termination::Cancelled);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                kill(&mut child, tree).unwrap_or_default();
+                return Ok(Termination::TimedOut);
+            }
+        }
+    }
+    Ok(match wait(&mut child, deadline, tree)? {
+        Some(status) => Termination::Exited(status),
+        None => Termination::TimedOut,
+    })
+}
@@ -0,0 +1,59 @@
+/// Quotes the argument for a POSIX shell, leaving it as is when it has no special characters.
+pub fn quote_posix(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Quotes the argument for `cmd.exe`, following the rules of `CommandLineToArgvW` and escaping
+/// the characters interpreted by `cmd.exe` itself with `^`.
+pub fn quote_windows(arg: &str) -> String {
+    let mut quoted = String::new();
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        quoted.push_str(arg);
+    } else {
+        quoted.push('"');
+        let mut backslashes = 0;
+        for c in arg.chars() {
+            match c {
+                '\\' => backslashes += 1,
+                '"' => {
+                    quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                    backslashes = 0;
+                }
+                _ => {
+                    quoted.push_str(&"\\".repeat(backslashes));
+                    backslashes = 0;
+                }
+            }
+            if c != '\\' {
+                quoted.push(c);
+            }
+        }
+        quoted.push_str(&"\\".repeat(backslashes * 2));
+        quoted.push('"');
+    }
+    let mut escaped = String::with_capacity(quoted.len());
+    for c in quoted.chars() {
+        if "()%!^\"<>&|".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quotes the arguments for the system shell, joining them with spaces.
+pub fn quote(args: &[&str]) -> String {
+    let quote = if cfg!(target_os = "windows") {
+        quote_windows
+    } else {
+        quote_posix
+    };
+    args.iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
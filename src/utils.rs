@@ -28,7 +28,7 @@ macro_rules! from_c_array {
         let mut array = Vec::new();
         for i in 0.. {
             let arg = *($array.offset(i));
-            if arg == ptr::null() {
+            if arg.is_null() {
                 break;
             }
             array.push(from_c_str!(arg).unwrap());
@@ -62,6 +62,16 @@ macro_rules! copy_c_str {
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! into_raw_bytes {
+    ($bytes:expr,$data:expr,$size:expr) => {
+        let buf = $bytes.into_boxed_slice();
+        *$size = buf.len();
+        *$data = Box::into_raw(buf) as *mut u8;
+    };
+}
+
 impl From<LockKeys> for DU_LOCKKEY {
     fn from(key: LockKeys) -> Self {
         match key {
@@ -1,19 +1,37 @@
-use libc::{c_char, c_int};
+use crate::DU_SPAWN_OPTIONS;
+use libc::{c_char, c_int, c_uint};
 use std::ffi::CStr;
-use std::io::Error;
+use std::fs::File;
+use std::io::{self, Error, ErrorKind};
 use std::mem;
-use winapi::shared::minwindef::{DWORD, MAX_PATH, WORD};
+use std::os::windows::io::AsRawHandle;
+use std::os::windows::process::CommandExt;
+use std::process::{Child, Command};
+use std::ptr;
+use std::sync::OnceLock;
+use winapi::shared::minwindef::{DWORD, FILETIME, LPVOID, MAX_PATH, WORD};
 use winapi::shared::winerror::{ERROR_ACCESS_DENIED, ERROR_PRIVILEGE_NOT_HELD};
 use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject};
 use winapi::um::minwinbase::SYSTEMTIME;
-use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+use winapi::um::processthreadsapi::{
+    GetProcessTimes, OpenProcess, OpenThread, ResumeThread, TerminateProcess,
+};
 use winapi::um::sysinfoapi::SetLocalTime;
-use winapi::um::winnt::PROCESS_TERMINATE;
+use winapi::um::winbase::{
+    CREATE_NEW_CONSOLE, CREATE_NEW_PROCESS_GROUP, CREATE_NO_WINDOW, CREATE_SUSPENDED,
+    DETACHED_PROCESS,
+};
+use winapi::um::winnt::{
+    JobObjectExtendedLimitInformation, HANDLE, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+    THREAD_SUSPEND_RESUME,
+};
 use winapi::um::{
     handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
     tlhelp32::{
-        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
-        TH32CS_SNAPPROCESS,
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, Thread32First, Thread32Next,
+        PROCESSENTRY32W, TH32CS_SNAPPROCESS, TH32CS_SNAPTHREAD, THREADENTRY32,
     },
 };
 
@@ -75,7 +93,7 @@ pub unsafe fn terminate(process_name: *const c_char) -> c_int {
                     .collect();
                 if name.to_ascii_lowercase() == process_name.to_ascii_lowercase() {
                     let process = OpenProcess(PROCESS_TERMINATE, 0, process_entry.th32ProcessID);
-                    if process != std::ptr::null_mut() {
+                    if !process.is_null() {
                         if TerminateProcess(process, 0) > 0 {
                             CloseHandle(process);
                             return 0;
@@ -99,3 +117,203 @@ pub unsafe fn terminate(process_name: *const c_char) -> c_int {
     CloseHandle(handle);
     ret
 }
+
+/// Applies the spawn options to the command through its creation flags, which are set all at
+/// once. `hidden` runs console programs without a console window, which cannot be combined with
+/// `new_console`, and `detached` runs them without any console in a new process group. Handles
+/// are always inherited, since the standard library creates every child inheriting them, so
+/// `inherit_handles` is required. `kill_with_parent` creates the child suspended, until `spawned`
+/// assigns it to the job object.
+pub fn spawn_options(cmd: &mut Command, options: &DU_SPAWN_OPTIONS) -> Result<(), c_int> {
+    if !options.inherit_handles
+        || (options.hidden && options.new_console)
+        || (options.detached && (options.new_console || options.kill_with_parent))
+    {
+        return Err(-1);
+    }
+    let mut flags = 0;
+    if options.hidden {
+        flags |= CREATE_NO_WINDOW;
+    }
+    if options.new_console {
+        flags |= CREATE_NEW_CONSOLE;
+    }
+    if options.detached {
+        flags |= DETACHED_PROCESS;
+    }
+    if options.new_process_group || options.detached {
+        flags |= CREATE_NEW_PROCESS_GROUP;
+    }
+    if options.kill_with_parent {
+        flags |= CREATE_SUSPENDED;
+    }
+    cmd.creation_flags(flags);
+    Ok(())
+}
+
+/// Spawns the command, which `spawned` assigns to the job object afterwards if
+/// `kill_with_parent` is set.
+pub fn spawn(cmd: &mut Command, _options: Option<&DU_SPAWN_OPTIONS>) -> io::Result<Child> {
+    cmd.spawn()
+}
+
+/// Job object which the children spawned with `kill_with_parent` are assigned to. Its handle is
+/// never closed, so the system closes it when the calling process dies, killing them all.
+static JOB: OnceLock<usize> = OnceLock::new();
+
+/// Applies the spawn options which require the running child, assigning it to the job object
+/// killing its processes on close if `kill_with_parent` is set. The child was created suspended,
+/// so it is only resumed once assigned, and all of its descendants belong to the job as well. The
+/// child is killed if it cannot be assigned.
+pub fn spawned(child: &mut Child, options: &DU_SPAWN_OPTIONS) -> Result<(), c_int> {
+    if !options.kill_with_parent {
+        return Ok(());
+    }
+    let job = *JOB.get_or_init(|| unsafe {
+        let job = CreateJobObjectW(ptr::null_mut(), ptr::null());
+        if job.is_null() {
+            return 0;
+        }
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = mem::zeroed();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &mut info as *mut _ as LPVOID,
+            mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as DWORD,
+        );
+        job as usize
+    });
+    let assigned = job != 0
+        && unsafe { AssignProcessToJobObject(job as HANDLE, child.as_raw_handle() as HANDLE) } != 0;
+    if !assigned || !unsafe { resume(child.id()) } {
+        child.kill().unwrap_or_default();
+        child.wait().map(|_| ()).unwrap_or_default();
+        return Err(-3);
+    }
+    Ok(())
+}
+
+/// Resumes the threads of the suspended process, returning whether any of them was resumed.
+unsafe fn resume(pid: DWORD) -> bool {
+    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+    if snapshot == INVALID_HANDLE_VALUE {
+        return false;
+    }
+    let mut resumed = false;
+    let mut entry: THREADENTRY32 = mem::zeroed();
+    entry.dwSize = mem::size_of::<THREADENTRY32>() as DWORD;
+    if Thread32First(snapshot, &mut entry) == 1 {
+        loop {
+            if entry.th32OwnerProcessID == pid {
+                let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                if !thread.is_null() {
+                    if ResumeThread(thread) != DWORD::MAX {
+                        resumed = true;
+                    }
+                    CloseHandle(thread);
+                }
+            }
+            if Thread32Next(snapshot, &mut entry) != 1 {
+                break;
+            }
+        }
+    }
+    CloseHandle(snapshot);
+    resumed
+}
+
+/// Returns the creation time of the process, or `None` if it cannot be opened.
+unsafe fn creation_time(process: HANDLE) -> Option<u64> {
+    let mut creation: FILETIME = mem::zeroed();
+    let mut exit: FILETIME = mem::zeroed();
+    let mut kernel: FILETIME = mem::zeroed();
+    let mut user: FILETIME = mem::zeroed();
+    if GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user) == 0 {
+        return None;
+    }
+    Some((u64::from(creation.dwHighDateTime) << 32) | u64::from(creation.dwLowDateTime))
+}
+
+/// Terminates all the descendants of the process, found through their parent process IDs. A
+/// process is only taken as a child if it was created after its parent, since the ID of a parent
+/// which already exited may have been reused.
+pub fn kill_tree(pid: c_uint) {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return;
+        }
+        let mut entries = Vec::new();
+        let mut entry: PROCESSENTRY32W = mem::zeroed();
+        entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as DWORD;
+        if Process32FirstW(snapshot, &mut entry) == 1 {
+            loop {
+                entries.push((entry.th32ProcessID, entry.th32ParentProcessID));
+                if Process32NextW(snapshot, &mut entry) != 1 {
+                    break;
+                }
+            }
+        }
+        CloseHandle(snapshot);
+        let root = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if root.is_null() {
+            return;
+        }
+        let created = creation_time(root);
+        CloseHandle(root);
+        let mut pending = match created {
+            Some(created) => vec![(pid, created)],
+            None => return,
+        };
+        let mut visited = vec![pid];
+        while let Some((parent, parent_created)) = pending.pop() {
+            for &(child, _) in entries.iter().filter(|&&(_, ppid)| ppid == parent) {
+                if visited.contains(&child) {
+                    continue;
+                }
+                let process = OpenProcess(
+                    PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_TERMINATE,
+                    0,
+                    child,
+                );
+                if process.is_null() {
+                    continue;
+                }
+                if let Some(created) = creation_time(process).filter(|&t| t >= parent_created) {
+                    visited.push(child);
+                    pending.push((child, created));
+                    TerminateProcess(process, 1);
+                }
+                CloseHandle(process);
+            }
+        }
+    }
+}
+
+/// Running the child as another user is not supported on Windows.
+pub unsafe fn credentials(
+    _cmd: &mut Command,
+    _user: Option<&str>,
+    _group: Option<&str>,
+    _groups: Option<Vec<&str>>,
+) -> Result<(), c_int> {
+    Err(-1)
+}
+
+/// Resource limits are not supported on Windows.
+pub fn limits(
+    _cmd: &mut Command,
+    _address_space: u64,
+    _cpu_time: u64,
+    _open_files: u64,
+    _nice: c_int,
+    _umask: c_int,
+) -> Result<(), c_int> {
+    Err(-1)
+}
+
+/// Pseudo-terminals are not supported on Windows.
+pub fn pty(_cmd: &mut Command, _rows: u16, _cols: u16) -> Result<File, Error> {
+    Err(Error::from(ErrorKind::Unsupported))
+}
@@ -0,0 +1,322 @@
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use data_encoding::BASE64;
+use libc::c_int;
+use openssl::hash::MessageDigest;
+use openssl::pkcs12::ParsedPkcs12_2;
+use openssl::sign::{Signer, Verifier};
+use openssl::x509::X509;
+use roxmltree::{Document, Node, NodeType};
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const C14N: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const SHA1: &str = "http://www.w3.org/2000/09/xmldsig#sha1";
+const RSA_SHA1: &str = "http://www.w3.org/2000/09/xmldsig#rsa-sha1";
+const SIGNATURE_VALUE: &str = "<SignatureValue></SignatureValue>";
+
+/// Serializes the `node` subtree as Canonical XML 1.0 (without comments), skipping the `exclude`
+/// subtree as required by the enveloped signature transform.
+pub fn canonicalize(node: Node, exclude: Option<Node>) -> String {
+    let mut out = String::new();
+    write_element(node, &[], true, exclude, &mut out);
+    out
+}
+
+fn write_element<'a>(
+    node: Node<'a, '_>,
+    parent_ns: &[(&'a str, &'a str)],
+    apex: bool,
+    exclude: Option<Node>,
+    out: &mut String,
+) {
+    if exclude == Some(node) {
+        return;
+    }
+    let text = node.document().input_text();
+    let ns: Vec<(&str, &str)> = node
+        .namespaces()
+        .filter(|ns| ns.uri() != XML_NS && !(ns.name().is_none() && ns.uri().is_empty()))
+        .map(|ns| (ns.name().unwrap_or_default(), ns.uri()))
+        .collect();
+    let mut decls: Vec<(&str, &str)> = ns
+        .iter()
+        .filter(|ns| !parent_ns.contains(ns))
+        .cloned()
+        .collect();
+    if !ns.iter().any(|ns| ns.0.is_empty()) && parent_ns.iter().any(|ns| ns.0.is_empty()) {
+        decls.push(("", ""));
+    }
+    decls.sort_unstable();
+    let mut attrs: Vec<(&str, &str, &str, &str)> = node
+        .attributes()
+        .map(|attr| {
+            (
+                attr.namespace().unwrap_or_default(),
+                attr.name(),
+                &text[attr.range_qname()],
+                attr.value(),
+            )
+        })
+        .collect();
+    if apex {
+        for ancestor in node.ancestors().skip(1).filter(|n| n.is_element()) {
+            for attr in ancestor.attributes() {
+                if attr.namespace() == Some(XML_NS)
+                    && !attrs.iter().any(|a| a.0 == XML_NS && a.1 == attr.name())
+                {
+                    attrs.push((XML_NS, attr.name(), &text[attr.range_qname()], attr.value()));
+                }
+            }
+        }
+    }
+    attrs.sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+    let name = qname(node);
+    out.push('<');
+    out.push_str(name);
+    for (prefix, uri) in decls {
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        escape_attr(uri, out);
+        out.push('"');
+    }
+    for (_, _, qname, value) in attrs {
+        out.push(' ');
+        out.push_str(qname);
+        out.push_str("=\"");
+        escape_attr(value, out);
+        out.push('"');
+    }
+    out.push('>');
+    for child in node.children() {
+        match child.node_type() {
+            NodeType::Element => write_element(child, &ns, false, exclude, out),
+            NodeType::Text => escape_text(child.text().unwrap_or_default(), out),
+            NodeType::PI => {
+                let pi = child.pi().unwrap();
+                out.push_str("<?");
+                out.push_str(pi.target);
+                if let Some(value) = pi.value {
+                    out.push(' ');
+                    out.push_str(value);
+                }
+                out.push_str("?>");
+            }
+            _ => {}
+        }
+    }
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+fn qname<'a>(node: Node<'a, '_>) -> &'a str {
+    let text = &node.document().input_text()[node.range().start + 1..];
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+fn escape_attr(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn escape_text(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn sha1_base64(data: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.input_str(data);
+    let mut digest = [0u8; 20];
+    hasher.result(&mut digest);
+    BASE64.encode(&digest)
+}
+
+fn find_by_id<'a, 'input>(doc: &'a Document<'input>, id: &str) -> Option<Node<'a, 'input>> {
+    doc.descendants().find(|n| {
+        n.is_element()
+            && ["Id", "ID", "id"]
+                .iter()
+                .any(|name| n.attribute(*name) == Some(id))
+    })
+}
+
+fn dsig_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| {
+        n.is_element() && n.tag_name().name() == name && n.tag_name().namespace() == Some(DSIG_NS)
+    })
+}
+
+fn algorithm<'a>(node: Option<Node<'a, '_>>) -> Option<&'a str> {
+    node.and_then(|n| n.attribute("Algorithm"))
+}
+
+fn strip_whitespace(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Signs the element named `tag` (or the root element if `None`) with an enveloped XMLDSig
+/// signature, inserting the `Signature` element right after the signed element.
+pub fn sign(xml: &str, tag: Option<&str>, pkcs12: &ParsedPkcs12_2) -> Result<String, c_int> {
+    let (pkey, cert) = match (&pkcs12.pkey, &pkcs12.cert) {
+        (Some(pkey), Some(cert)) => (pkey, cert),
+        _ => return Err(-4),
+    };
+    let doc = Document::parse(xml).map_err(|_| -6)?;
+    let (node, uri) = match tag {
+        Some(tag) => {
+            let node = doc
+                .descendants()
+                .find(|n| n.is_element() && n.tag_name().name() == tag)
+                .ok_or(-7)?;
+            let id = ["Id", "ID", "id"]
+                .iter()
+                .find_map(|name| node.attribute(*name))
+                .ok_or(-7)?;
+            (node, format!("#{}", id))
+        }
+        None => (doc.root_element(), String::new()),
+    };
+    let position = if node.parent() == Some(doc.root()) {
+        match xml[..node.range().end].rfind("</") {
+            Some(position) if position > node.range().start => position,
+            _ => return Err(-7),
+        }
+    } else {
+        node.range().end
+    };
+    let digest = sha1_base64(&canonicalize(node, None));
+    let der = cert.to_der().map_err(|_| -5)?;
+    let signature = format!(
+        "<Signature xmlns=\"{}\"><SignedInfo><CanonicalizationMethod Algorithm=\"{}\"/>\
+         <SignatureMethod Algorithm=\"{}\"/><Reference URI=\"{}\"><Transforms>\
+         <Transform Algorithm=\"{}\"/><Transform Algorithm=\"{}\"/></Transforms>\
+         <DigestMethod Algorithm=\"{}\"/><DigestValue>{}</DigestValue></Reference></SignedInfo>\
+         {}<KeyInfo><X509Data><X509Certificate>{}</X509Certificate></X509Data></KeyInfo>\
+         </Signature>",
+        DSIG_NS,
+        C14N,
+        RSA_SHA1,
+        uri,
+        ENVELOPED_SIGNATURE,
+        C14N,
+        SHA1,
+        digest,
+        SIGNATURE_VALUE,
+        BASE64.encode(&der)
+    );
+    let mut signed = String::with_capacity(xml.len() + signature.len() + 512);
+    signed.push_str(&xml[..position]);
+    signed.push_str(&signature);
+    signed.push_str(&xml[position..]);
+    let canonical = {
+        let doc = Document::parse(&signed).map_err(|_| -6)?;
+        let signature = doc
+            .descendants()
+            .find(|n| n.is_element() && n.range().start == position)
+            .ok_or(-5)?;
+        canonicalize(dsig_child(signature, "SignedInfo").ok_or(-5)?, None)
+    };
+    let mut signer = Signer::new(MessageDigest::sha1(), pkey).map_err(|_| -5)?;
+    let value = signer
+        .sign_oneshot_to_vec(canonical.as_bytes())
+        .map_err(|_| -5)?;
+    let value_position = position + signed[position..].find(SIGNATURE_VALUE).ok_or(-5)?;
+    signed.replace_range(
+        value_position..value_position + SIGNATURE_VALUE.len(),
+        &format!("<SignatureValue>{}</SignatureValue>", BASE64.encode(&value)),
+    );
+    Ok(signed)
+}
+
+/// Verifies the first enveloped XMLDSig signature found in `xml` against the certificate embedded in
+/// its `KeyInfo`.
+pub fn verify(xml: &str) -> Result<(), c_int> {
+    let doc = Document::parse(xml).map_err(|_| -2)?;
+    let signature = doc
+        .descendants()
+        .find(|n| {
+            n.is_element()
+                && n.tag_name().name() == "Signature"
+                && n.tag_name().namespace() == Some(DSIG_NS)
+        })
+        .ok_or(-3)?;
+    let signed_info = dsig_child(signature, "SignedInfo").ok_or(-3)?;
+    if algorithm(dsig_child(signed_info, "CanonicalizationMethod")) != Some(C14N)
+        || algorithm(dsig_child(signed_info, "SignatureMethod")) != Some(RSA_SHA1)
+    {
+        return Err(-6);
+    }
+    let reference = dsig_child(signed_info, "Reference").ok_or(-3)?;
+    if algorithm(dsig_child(reference, "DigestMethod")) != Some(SHA1) {
+        return Err(-6);
+    }
+    let mut enveloped = false;
+    if let Some(transforms) = dsig_child(reference, "Transforms") {
+        for transform in transforms.children().filter(|n| n.is_element()) {
+            match transform.attribute("Algorithm") {
+                Some(ENVELOPED_SIGNATURE) => enveloped = true,
+                Some(C14N) => {}
+                _ => return Err(-6),
+            }
+        }
+    }
+    let node = match reference.attribute("URI").unwrap_or_default() {
+        "" => doc.root_element(),
+        uri if uri.starts_with('#') => find_by_id(&doc, &uri[1..]).ok_or(-4)?,
+        _ => return Err(-6),
+    };
+    let exclude = if enveloped { Some(signature) } else { None };
+    let digest = dsig_child(reference, "DigestValue")
+        .and_then(|n| n.text())
+        .map(strip_whitespace)
+        .unwrap_or_default();
+    if sha1_base64(&canonicalize(node, exclude)) != digest {
+        return Err(-4);
+    }
+    let value = dsig_child(signature, "SignatureValue")
+        .and_then(|n| n.text())
+        .map(strip_whitespace)
+        .and_then(|value| BASE64.decode(value.as_bytes()).ok())
+        .ok_or(-5)?;
+    let cert = dsig_child(signature, "KeyInfo")
+        .and_then(|n| dsig_child(n, "X509Data"))
+        .and_then(|n| dsig_child(n, "X509Certificate"))
+        .and_then(|n| n.text())
+        .map(strip_whitespace)
+        .and_then(|cert| BASE64.decode(cert.as_bytes()).ok())
+        .and_then(|der| X509::from_der(&der).ok())
+        .ok_or(-5)?;
+    let pkey = cert.public_key().map_err(|_| -5)?;
+    let mut verifier = Verifier::new(MessageDigest::sha1(), &pkey).map_err(|_| -5)?;
+    match verifier.verify_oneshot(&value, canonicalize(signed_info, None).as_bytes()) {
+        Ok(true) => Ok(()),
+        _ => Err(-5),
+    }
+}